@@ -0,0 +1,8 @@
+-- How far each history source has been imported, so importing it again only
+-- adds what is new. See atuin_client::import::Watermark
+create table if not exists import_watermarks (
+	source text primary key,
+	byte_offset integer not null,
+	timestamp integer not null,
+	hash text not null
+);
@@ -0,0 +1,5 @@
+-- History that is kept on this machine and never synced, such as commands
+-- that contain secrets
+create table if not exists local_only (
+	history_id text primary key
+);
@@ -0,0 +1,4 @@
+-- History that is kept no matter what history_retention says
+create table if not exists pinned (
+	history_id text primary key
+);
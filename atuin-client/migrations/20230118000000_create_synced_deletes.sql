@@ -0,0 +1,5 @@
+-- Deletes that the sync server already knows about, either because they were
+-- sent to it, or came from it
+create table if not exists synced_deletes (
+	history_id text primary key
+);
@@ -9,8 +9,8 @@ use reqwest::{
 use sodiumoxide::crypto::secretbox;
 
 use atuin_common::api::{
-    AddHistoryRequest, CountResponse, ErrorResponse, IndexResponse, LoginRequest, LoginResponse,
-    RegisterResponse, SyncHistoryResponse,
+    AddHistoryRequest, CountResponse, DeleteHistoryRequest, ErrorResponse, IndexResponse,
+    LoginRequest, LoginResponse, RegisterResponse, SyncDeletedResponse, SyncHistoryResponse,
 };
 use semver::Version;
 
@@ -178,4 +178,48 @@ impl<'a> Client<'a> {
 
         Ok(())
     }
+
+    /// Returns false if the server is too old to delete history
+    pub async fn delete_history(&self, ids: &[String]) -> Result<bool> {
+        let url = format!("{}/history", self.sync_addr);
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self
+            .client
+            .delete(url)
+            .json(&DeleteHistoryRequest { ids: ids.to_vec() })
+            .send()
+            .await?;
+
+        match resp.status() {
+            StatusCode::OK => Ok(true),
+            StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => Ok(false),
+            status => bail!("failed to delete history: {}", status),
+        }
+    }
+
+    /// The ids of the history deleted since `since`, by any client, or
+    /// `None` if the server is too old to delete history
+    pub async fn deleted_history(
+        &self,
+        since: chrono::DateTime<Utc>,
+    ) -> Result<Option<Vec<String>>> {
+        let url = format!(
+            "{}/sync/deleted?since={}",
+            self.sync_addr,
+            urlencoding::encode(since.to_rfc3339().as_str()),
+        );
+
+        let resp = self.client.get(url).send().await?;
+
+        match resp.status() {
+            StatusCode::OK => {}
+            StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => return Ok(None),
+            status => bail!("failed to get deleted history: {}", status),
+        }
+
+        let deleted = resp.json::<SyncDeletedResponse>().await?;
+
+        Ok(Some(deleted.ids))
+    }
 }
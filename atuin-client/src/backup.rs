@@ -0,0 +1,160 @@
+// A backup is a single file with all of the local history, for machines that
+// never sync, or to keep what has not been synced yet.
+//
+// The history is serialized with msgpack, compressed, and then encrypted with
+// the same key as sync. Only the version of the format is left in the clear.
+
+use std::{
+    collections::HashSet,
+    io::{Read, Write},
+};
+
+use eyre::{bail, Context, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::{Database, SearchFilter},
+    encryption::{decrypt_bytes, encrypt_bytes, EncryptedHistory, Key},
+    history::History,
+};
+
+const VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupFile {
+    version: u32,
+    data: EncryptedHistory,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Backup {
+    pub history: Vec<History>,
+    /// The ids of the history that is never synced, so it stays that way once restored
+    pub local_only: Vec<String>,
+}
+
+impl Backup {
+    /// Everything in `db`
+    pub async fn create(db: &impl Database) -> Result<Self> {
+        let history = db.query_history(&SearchFilter::default()).await?;
+        let local_only = db.local_only_ids().await?;
+
+        Ok(Self {
+            history,
+            local_only,
+        })
+    }
+
+    pub fn encrypt(&self, key: &Key) -> Result<Vec<u8>> {
+        let mut compressed = GzEncoder::new(Vec::new(), Compression::default());
+        compressed.write_all(&rmp_serde::to_vec(self)?)?;
+
+        let file = BackupFile {
+            version: VERSION,
+            data: encrypt_bytes(&compressed.finish()?, key),
+        };
+
+        Ok(rmp_serde::to_vec(&file)?)
+    }
+
+    pub fn decrypt(bytes: &[u8], key: &Key) -> Result<Self> {
+        let file: BackupFile = rmp_serde::from_slice(bytes).wrap_err("not an atuin backup")?;
+        if file.version != VERSION {
+            bail!(
+                "backup format {} is not supported by this version of atuin",
+                file.version
+            );
+        }
+
+        let compressed = decrypt_bytes(&file.data, key)?;
+        let mut buf = Vec::new();
+        GzDecoder::new(compressed.as_slice()).read_to_end(&mut buf)?;
+
+        Ok(rmp_serde::from_slice(&buf)?)
+    }
+
+    /// Merge the backup into `db`. History it already has, or has deleted, is skipped
+    pub async fn restore(self, db: &mut impl Database) -> Result<()> {
+        let local_only: HashSet<String> = self.local_only.into_iter().collect();
+        let (local, synced): (Vec<History>, Vec<History>) = self
+            .history
+            .into_iter()
+            .partition(|h| local_only.contains(&h.id));
+
+        db.save_bulk(&synced).await?;
+        for h in &local {
+            db.save_local_only(h).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sodiumoxide::crypto::secretbox;
+
+    use super::*;
+    use crate::database::Sqlite;
+
+    fn history(command: &str) -> History {
+        History::new(
+            chrono::Utc::now(),
+            command.to_string(),
+            "/home/ellie".to_string(),
+            0,
+            1,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn encrypt_decrypt() {
+        let key = secretbox::gen_key();
+        let backup = Backup {
+            history: vec![history("ls"), history("mysql --password=hunter2")],
+            local_only: vec!["id".to_string()],
+        };
+
+        let bytes = backup.encrypt(&key).unwrap();
+        assert_eq!(Backup::decrypt(&bytes, &key).unwrap(), backup);
+
+        let other = secretbox::gen_key();
+        assert!(Backup::decrypt(&bytes, &other).is_err());
+        assert!(Backup::decrypt(b"not a backup", &key).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn create_restore() {
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        let ls = history("ls");
+        let secret = history("mysql --password=hunter2");
+        db.save(&ls).await.unwrap();
+        db.save_local_only(&secret).await.unwrap();
+
+        let backup = Backup::create(&db).await.unwrap();
+        assert_eq!(backup.history.len(), 2);
+
+        let mut restored = Sqlite::new("sqlite::memory:").await.unwrap();
+        restored.save(&ls).await.unwrap();
+        restored.save(&history("cd /")).await.unwrap();
+        backup.restore(&mut restored).await.unwrap();
+
+        // merged, rather than replaced, and still never synced
+        assert_eq!(restored.history_count().await.unwrap(), 3);
+        assert_eq!(
+            restored.local_only_ids().await.unwrap(),
+            [secret.id.as_str()]
+        );
+
+        // unless it was already here, and so may have been synced
+        let mut synced = Sqlite::new("sqlite::memory:").await.unwrap();
+        synced.save(&secret).await.unwrap();
+        let backup = Backup::create(&db).await.unwrap();
+        backup.restore(&mut synced).await.unwrap();
+        assert_eq!(synced.history_count().await.unwrap(), 2);
+        assert!(synced.local_only_ids().await.unwrap().is_empty());
+    }
+}
@@ -16,6 +16,7 @@ use sqlx::{
 use super::{
     event::{Event, EventType},
     history::History,
+    import::Watermark,
     ordering,
     settings::{FilterMode, SearchMode},
 };
@@ -24,6 +25,7 @@ pub struct Context {
     session: String,
     cwd: String,
     hostname: String,
+    git_root: Option<String>,
 }
 
 pub fn current_context() -> Context {
@@ -33,11 +35,158 @@ pub fn current_context() -> Context {
     };
     let hostname = format!("{}:{}", whoami::hostname(), whoami::username());
     let cwd = utils::get_current_dir();
+    let git_root = utils::git_root(&cwd).map(|p| p.to_string_lossy().into_owned());
 
     Context {
         session,
         hostname,
         cwd,
+        git_root,
+    }
+}
+
+impl Context {
+    /// Whether the current directory is inside a git repository, for `FilterMode::Workspace`
+    pub fn in_workspace(&self) -> bool {
+        self.git_root.is_some()
+    }
+}
+
+/// How often a single command has been ran, and how often it succeeded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CommandStats {
+    pub total: i64,
+    pub success: i64,
+}
+
+/// Optional predicates to narrow down a history query, on top of the `FilterMode`.
+/// Every value is passed to sqlite as a bound parameter.
+#[derive(Clone, Debug, Default)]
+pub struct SearchFilter {
+    /// Exact match on the whole command
+    pub command: Option<String>,
+    pub exit: Option<i64>,
+    pub exclude_exit: Option<i64>,
+    pub cwd: Option<String>,
+    pub exclude_cwd: Option<String>,
+    pub session: Option<String>,
+    pub exclude_session: Option<String>,
+    /// Matched against the host half of `hostname`
+    pub host: Option<String>,
+    pub exclude_host: Option<String>,
+    /// Matched against the user half of `hostname`
+    pub user: Option<String>,
+    pub exclude_user: Option<String>,
+    /// Duration in nanoseconds
+    pub min_duration: Option<i64>,
+    /// Duration in nanoseconds
+    pub max_duration: Option<i64>,
+    /// Unix timestamp in nanoseconds
+    pub before: Option<i64>,
+    /// Unix timestamp in nanoseconds
+    pub after: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+enum SqlParam {
+    Int(i64),
+    Text(String),
+}
+
+// `hostname` is stored as "host:user", split the same way as when formatting history
+const HOST_EXPR: &str =
+    "(case when instr(hostname, ':') > 0 then substr(hostname, 1, instr(hostname, ':') - 1) else hostname end)";
+const USER_EXPR: &str =
+    "(case when instr(hostname, ':') > 0 then substr(hostname, instr(hostname, ':') + 1) else '' end)";
+
+impl SearchFilter {
+    /// Add a `?` condition for every predicate that is set, and return the values to bind,
+    /// in the order their placeholders appear in the query.
+    fn apply(&self, sql: &mut SqlBuilder) -> Vec<SqlParam> {
+        let mut params = Vec::new();
+
+        if let Some(command) = &self.command {
+            sql.and_where_eq("command", "?");
+            params.push(SqlParam::Text(command.clone()));
+        }
+
+        if let Some(exit) = self.exit {
+            sql.and_where_eq("exit", "?");
+            params.push(SqlParam::Int(exit));
+        }
+
+        if let Some(exit) = self.exclude_exit {
+            sql.and_where_ne("exit", "?");
+            params.push(SqlParam::Int(exit));
+        }
+
+        if let Some(cwd) = &self.cwd {
+            sql.and_where_eq("cwd", "?");
+            params.push(SqlParam::Text(cwd.clone()));
+        }
+
+        if let Some(cwd) = &self.exclude_cwd {
+            sql.and_where_ne("cwd", "?");
+            params.push(SqlParam::Text(cwd.clone()));
+        }
+
+        if let Some(session) = &self.session {
+            sql.and_where_eq("session", "?");
+            params.push(SqlParam::Text(session.clone()));
+        }
+
+        if let Some(session) = &self.exclude_session {
+            sql.and_where_ne("session", "?");
+            params.push(SqlParam::Text(session.clone()));
+        }
+
+        if let Some(host) = &self.host {
+            sql.and_where_eq(HOST_EXPR, "?");
+            params.push(SqlParam::Text(host.clone()));
+        }
+
+        if let Some(host) = &self.exclude_host {
+            sql.and_where_ne(HOST_EXPR, "?");
+            params.push(SqlParam::Text(host.clone()));
+        }
+
+        if let Some(user) = &self.user {
+            sql.and_where_eq(USER_EXPR, "?");
+            params.push(SqlParam::Text(user.clone()));
+        }
+
+        if let Some(user) = &self.exclude_user {
+            sql.and_where_ne(USER_EXPR, "?");
+            params.push(SqlParam::Text(user.clone()));
+        }
+
+        if let Some(duration) = self.min_duration {
+            sql.and_where_ge("duration", "?");
+            params.push(SqlParam::Int(duration));
+        }
+
+        if let Some(duration) = self.max_duration {
+            // commands that are still running have no duration yet
+            sql.and_where_ge("duration", 0);
+            sql.and_where_le("duration", "?");
+            params.push(SqlParam::Int(duration));
+        }
+
+        if let Some(before) = self.before {
+            sql.and_where_lt("timestamp", "?");
+            params.push(SqlParam::Int(before));
+        }
+
+        if let Some(after) = self.after {
+            sql.and_where_gt("timestamp", "?");
+            params.push(SqlParam::Int(after));
+        }
+
+        if let Some(limit) = self.limit {
+            sql.limit(limit);
+        }
+
+        params
     }
 }
 
@@ -45,8 +194,33 @@ pub fn current_context() -> Context {
 pub trait Database: Send + Sync {
     async fn save(&mut self, h: &History) -> Result<()>;
     async fn save_bulk(&mut self, h: &[History]) -> Result<()>;
+    /// Save history that is never synced. History that is already saved is left as it is
+    async fn save_local_only(&mut self, h: &History) -> Result<()>;
+    /// The ids of all history that is never synced
+    async fn local_only_ids(&self) -> Result<Vec<String>>;
+
+    /// Keep history from being expired by `history_retention`
+    async fn pin(&self, id: &str) -> Result<()>;
+    async fn unpin(&self, id: &str) -> Result<()>;
+    async fn pinned(&self) -> Result<Vec<History>>;
+    /// History that is not pinned, and either started before `cutoff` or is
+    /// not one of the newest `max_entries` of it. Newest first
+    async fn expired(
+        &self,
+        cutoff: Option<chrono::DateTime<Utc>>,
+        max_entries: Option<i64>,
+    ) -> Result<Vec<History>>;
+
+    async fn delete(&mut self, h: &History) -> Result<()>;
+    async fn delete_bulk(&mut self, h: &[History]) -> Result<()>;
+    /// The ids of deleted history that the sync server does not know about yet
+    async fn unsynced_deletes(&self) -> Result<Vec<String>>;
+    async fn save_synced_deletes(&self, ids: &[String]) -> Result<()>;
 
     async fn load(&self, id: &str) -> Result<History>;
+    /// Whether saving `h` would be ignored, because it or the same command run at the
+    /// same time in the same directory is already saved, or because it was deleted
+    async fn exists(&self, h: &History) -> Result<bool>;
     async fn list(
         &self,
         filter: FilterMode,
@@ -62,29 +236,30 @@ pub trait Database: Send + Sync {
 
     async fn update(&self, h: &History) -> Result<()>;
     async fn history_count(&self) -> Result<i64>;
+    /// The amount of history that is synced, leaving out what is local only
+    async fn sync_count(&self) -> Result<i64>;
+    async fn command_stats(&self, command: &str) -> Result<CommandStats>;
     async fn event_count(&self) -> Result<i64>;
     async fn merge_events(&self) -> Result<i64>;
 
     async fn first(&self) -> Result<History>;
     async fn last(&self) -> Result<History>;
+    /// History to sync from before `timestamp`, newest first
     async fn before(&self, timestamp: chrono::DateTime<Utc>, count: i64) -> Result<Vec<History>>;
 
-    // Yes I know, it's a lot.
-    // Could maybe break it down to a searchparams struct or smth but that feels a little... pointless.
-    // Been debating maybe a DSL for search? eg "before:time limit:1 the query"
-    #[allow(clippy::too_many_arguments)]
     async fn search(
         &self,
         search_mode: SearchMode,
         filter: FilterMode,
         context: &Context,
         query: &str,
-        limit: Option<i64>,
-        before: Option<i64>,
-        after: Option<i64>,
+        filter_options: &SearchFilter,
     ) -> Result<Vec<History>>;
 
-    async fn query_history(&self, query: &str) -> Result<Vec<History>>;
+    async fn query_history(&self, filter_options: &SearchFilter) -> Result<Vec<History>>;
+
+    async fn import_watermark(&self, source: &str) -> Result<Option<Watermark>>;
+    async fn save_import_watermark(&self, source: &str, watermark: &Watermark) -> Result<()>;
 }
 
 // Intended for use on a developer machine and not a sync server.
@@ -145,10 +320,13 @@ impl Sqlite {
         Ok(())
     }
 
-    async fn save_raw(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, h: &History) -> Result<()> {
-        sqlx::query(
+    /// Returns false if the history was not inserted, as it was already there or deleted
+    async fn save_raw(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, h: &History) -> Result<bool> {
+        // never bring back history that has been deleted, eg when it is downloaded again by sync
+        let res = sqlx::query(
             "insert or ignore into history(id, timestamp, duration, exit, command, cwd, session, hostname)
-                values(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                select ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8
+                where not exists (select 1 from events where event_type = 'delete' and history_id = ?1)",
         )
         .bind(h.id.as_str())
         .bind(h.timestamp.timestamp_nanos())
@@ -161,7 +339,25 @@ impl Sqlite {
         .execute(tx)
         .await?;
 
-        Ok(())
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn query_filtered(
+        &self,
+        sql: &SqlBuilder,
+        params: Vec<SqlParam>,
+    ) -> Result<Vec<History>> {
+        let sql = sql.sql().expect("bug in filtered query. please report");
+
+        let mut query = sqlx::query(&sql);
+        for param in params {
+            query = match param {
+                SqlParam::Int(i) => query.bind(i),
+                SqlParam::Text(s) => query.bind(s),
+            };
+        }
+
+        query.map(Self::query_history).fetch_all(&self.pool).await
     }
 
     fn query_history(row: SqliteRow) -> History {
@@ -208,6 +404,141 @@ impl Database for Sqlite {
         Ok(())
     }
 
+    async fn save_local_only(&mut self, h: &History) -> Result<()> {
+        debug!("saving local only history to sqlite");
+        let event = Event::new_create(h);
+
+        let mut tx = self.pool.begin().await?;
+        // history that is already here may have been synced, so it stays as it is
+        if Self::save_raw(&mut tx, h).await? {
+            Self::save_event(&mut tx, &event).await?;
+            sqlx::query("insert or ignore into local_only(history_id) values(?1)")
+                .bind(h.id.as_str())
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn local_only_ids(&self) -> Result<Vec<String>> {
+        sqlx::query_scalar("select history_id from local_only")
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    async fn pin(&self, id: &str) -> Result<()> {
+        sqlx::query("insert or ignore into pinned(history_id) values(?1)")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn unpin(&self, id: &str) -> Result<()> {
+        sqlx::query("delete from pinned where history_id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn pinned(&self) -> Result<Vec<History>> {
+        sqlx::query(
+            "select * from history where id in (select history_id from pinned) order by timestamp desc",
+        )
+        .map(Self::query_history)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn expired(
+        &self,
+        cutoff: Option<chrono::DateTime<Utc>>,
+        max_entries: Option<i64>,
+    ) -> Result<Vec<History>> {
+        debug!(
+            "listing history from before {:?} or past {:?} entries",
+            cutoff, max_entries
+        );
+
+        // with no cutoff nothing is before i64::MIN, and with no max_entries the offset is past all history
+        sqlx::query(
+            "select * from history
+                where id not in (select history_id from pinned)
+                and (timestamp < ?1 or id in (
+                    select id from history
+                    where id not in (select history_id from pinned)
+                    order by timestamp desc limit -1 offset ?2
+                ))
+                order by timestamp desc",
+        )
+        .bind(cutoff.map_or(i64::MIN, |c| c.timestamp_nanos()))
+        .bind(max_entries.unwrap_or(i64::MAX))
+        .map(Self::query_history)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn delete(&mut self, h: &History) -> Result<()> {
+        self.delete_bulk(std::slice::from_ref(h)).await
+    }
+
+    async fn delete_bulk(&mut self, h: &[History]) -> Result<()> {
+        debug!("deleting history from sqlite");
+
+        let mut tx = self.pool.begin().await?;
+
+        for i in h {
+            sqlx::query("delete from history where id = ?1")
+                .bind(i.id.as_str())
+                .execute(&mut tx)
+                .await?;
+            sqlx::query("delete from local_only where history_id = ?1")
+                .bind(i.id.as_str())
+                .execute(&mut tx)
+                .await?;
+            sqlx::query("delete from pinned where history_id = ?1")
+                .bind(i.id.as_str())
+                .execute(&mut tx)
+                .await?;
+
+            Self::save_event(&mut tx, &Event::new_delete(&i.id)).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn unsynced_deletes(&self) -> Result<Vec<String>> {
+        sqlx::query_scalar(
+            "select history_id from events
+                where event_type = 'delete'
+                and history_id not in (select history_id from synced_deletes)",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn save_synced_deletes(&self, ids: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for id in ids {
+            sqlx::query("insert or ignore into synced_deletes(history_id) values(?1)")
+                .bind(id.as_str())
+                .execute(&mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
     async fn load(&self, id: &str) -> Result<History> {
         debug!("loading history item {}", id);
 
@@ -220,6 +551,21 @@ impl Database for Sqlite {
         Ok(res)
     }
 
+    async fn exists(&self, h: &History) -> Result<bool> {
+        let res: (bool,) = sqlx::query_as(
+            "select exists(select 1 from history where id = ?1 or (timestamp = ?2 and cwd = ?3 and command = ?4))
+                or exists(select 1 from events where event_type = 'delete' and history_id = ?1)",
+        )
+        .bind(h.id.as_str())
+        .bind(h.timestamp.timestamp_nanos())
+        .bind(h.cwd.as_str())
+        .bind(h.command.as_str())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(res.0)
+    }
+
     async fn update(&self, h: &History) -> Result<()> {
         debug!("updating sqlite history");
 
@@ -255,12 +601,7 @@ impl Database for Sqlite {
         let mut query = SqlBuilder::select_from(SqlName::new("history").alias("h").baquoted());
         query.field("*").order_desc("timestamp");
 
-        match filter {
-            FilterMode::Global => &mut query,
-            FilterMode::Host => query.and_where_eq("hostname", quote(&context.hostname)),
-            FilterMode::Session => query.and_where_eq("session", quote(&context.session)),
-            FilterMode::Directory => query.and_where_eq("cwd", quote(&context.cwd)),
-        };
+        query.filter_mode_condition(filter, context);
 
         if unique {
             query.and_where_eq(
@@ -325,7 +666,9 @@ impl Database for Sqlite {
 
     async fn before(&self, timestamp: chrono::DateTime<Utc>, count: i64) -> Result<Vec<History>> {
         let res = sqlx::query(
-            "select * from history where timestamp < ?1 order by timestamp desc limit ?2",
+            "select * from history
+                where timestamp < ?1 and id not in (select history_id from local_only)
+                order by timestamp desc limit ?2",
         )
         .bind(timestamp.timestamp_nanos())
         .bind(count)
@@ -359,6 +702,7 @@ impl Database for Sqlite {
                 cwd: String::from(""),
                 session: String::from(""),
                 hostname: String::from(""),
+                git_root: None,
             };
 
             // We're just gonna load everything into memory here. That sucks, I know, sorry.
@@ -387,15 +731,35 @@ impl Database for Sqlite {
         Ok(res.0)
     }
 
+    async fn sync_count(&self) -> Result<i64> {
+        let res: (i64,) = sqlx::query_as(
+            "select count(1) from history where id not in (select history_id from local_only)",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(res.0)
+    }
+
+    async fn command_stats(&self, command: &str) -> Result<CommandStats> {
+        // success mirrors History::success, so commands that are still running count
+        let (total, success): (i64, i64) = sqlx::query_as(
+            "select count(1), coalesce(sum(exit = 0 or duration = -1), 0) from history where command = ?1",
+        )
+        .bind(command)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(CommandStats { total, success })
+    }
+
     async fn search(
         &self,
         search_mode: SearchMode,
         filter: FilterMode,
         context: &Context,
         query: &str,
-        limit: Option<i64>,
-        before: Option<i64>,
-        after: Option<i64>,
+        filter_options: &SearchFilter,
     ) -> Result<Vec<History>> {
         let mut sql = SqlBuilder::select_from("history");
 
@@ -403,24 +767,9 @@ impl Database for Sqlite {
             .having("max(timestamp)")
             .order_desc("timestamp");
 
-        if let Some(limit) = limit {
-            sql.limit(limit);
-        }
-
-        if let Some(after) = after {
-            sql.and_where_gt("timestamp", after);
-        }
-
-        if let Some(before) = before {
-            sql.and_where_lt("timestamp", before);
-        }
+        let params = filter_options.apply(&mut sql);
 
-        match filter {
-            FilterMode::Global => &mut sql,
-            FilterMode::Host => sql.and_where_eq("hostname", quote(&context.hostname)),
-            FilterMode::Session => sql.and_where_eq("session", quote(&context.session)),
-            FilterMode::Directory => sql.and_where_eq("cwd", quote(&context.cwd)),
-        };
+        sql.filter_mode_condition(filter, context);
 
         let orig_query = query;
         let query = query.replace('*', "%"); // allow wildcard char
@@ -474,23 +823,45 @@ impl Database for Sqlite {
             }
         };
 
-        let query = sql.sql().expect("bug in search query. please report");
-
-        let res = sqlx::query(&query)
-            .map(Self::query_history)
-            .fetch_all(&self.pool)
-            .await?;
+        let res = self.query_filtered(&sql, params).await?;
 
         Ok(ordering::reorder_fuzzy(search_mode, orig_query, res))
     }
 
-    async fn query_history(&self, query: &str) -> Result<Vec<History>> {
-        let res = sqlx::query(query)
-            .map(Self::query_history)
-            .fetch_all(&self.pool)
-            .await?;
+    async fn query_history(&self, filter_options: &SearchFilter) -> Result<Vec<History>> {
+        let mut sql = SqlBuilder::select_from("history");
+        sql.order_desc("timestamp");
 
-        Ok(res)
+        let params = filter_options.apply(&mut sql);
+
+        self.query_filtered(&sql, params).await
+    }
+
+    async fn import_watermark(&self, source: &str) -> Result<Option<Watermark>> {
+        sqlx::query("select * from import_watermarks where source = ?1")
+            .bind(source)
+            .map(|row: SqliteRow| Watermark {
+                offset: row.get("byte_offset"),
+                timestamp: row.get("timestamp"),
+                hash: row.get("hash"),
+            })
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn save_import_watermark(&self, source: &str, watermark: &Watermark) -> Result<()> {
+        sqlx::query(
+            "insert or replace into import_watermarks(source, byte_offset, timestamp, hash)
+                values(?1, ?2, ?3, ?4)",
+        )
+        .bind(source)
+        .bind(watermark.offset)
+        .bind(watermark.timestamp)
+        .bind(watermark.hash.as_str())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
     }
 }
 
@@ -510,10 +881,11 @@ mod test {
             hostname: "test:host".to_string(),
             session: "beepboopiamasession".to_string(),
             cwd: "/home/ellie".to_string(),
+            git_root: None,
         };
 
         let results = db
-            .search(mode, filter_mode, &context, query, None, None, None)
+            .search(mode, filter_mode, &context, query, &SearchFilter::default())
             .await?;
 
         assert_eq!(
@@ -701,12 +1073,417 @@ mod test {
             .unwrap();
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_search_filter_options() {
+        let context = Context {
+            hostname: "test:host".to_string(),
+            session: "beepboopiamasession".to_string(),
+            cwd: "/home/ellie".to_string(),
+            git_root: None,
+        };
+
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        for (i, &(cmd, cwd, exit)) in [
+            ("ls", "/home/ellie", 0),
+            ("false", "/home/ellie", 1),
+            ("cat notes", "/home/ellie/it's here", 0),
+            ("make", "/home/ellie/it's here", 2),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let history = History::new(
+                Utc.timestamp_nanos((i as i64 + 1) * 1000),
+                cmd.to_string(),
+                cwd.to_string(),
+                exit,
+                1,
+                Some("beep boop".to_string()),
+                Some("booop".to_string()),
+            );
+            db.save(&history).await.unwrap();
+        }
+
+        // filtering happens before the limit, so we still get a full page
+        let results = db
+            .search(
+                SearchMode::Fuzzy,
+                FilterMode::Global,
+                &context,
+                "",
+                &SearchFilter {
+                    exit: Some(0),
+                    limit: Some(2),
+                    ..SearchFilter::default()
+                },
+            )
+            .await
+            .unwrap();
+        let commands: Vec<&str> = results.iter().map(|h| h.command.as_str()).collect();
+        assert_eq!(commands, vec!["cat notes", "ls"]);
+
+        let results = db
+            .query_history(&SearchFilter {
+                cwd: Some("/home/ellie/it's here".to_string()),
+                exclude_exit: Some(2),
+                ..SearchFilter::default()
+            })
+            .await
+            .unwrap();
+        let commands: Vec<&str> = results.iter().map(|h| h.command.as_str()).collect();
+        assert_eq!(commands, vec!["cat notes"]);
+
+        let results = db
+            .query_history(&SearchFilter {
+                exclude_cwd: Some("/home/ellie".to_string()),
+                before: Some(4000),
+                ..SearchFilter::default()
+            })
+            .await
+            .unwrap();
+        let commands: Vec<&str> = results.iter().map(|h| h.command.as_str()).collect();
+        assert_eq!(commands, vec!["cat notes"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_search_host_user_duration() {
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        for (cmd, hostname, duration) in &[
+            ("sleep 10", "laptop:ellie", 10_000_000_000),
+            ("ls", "laptop:root", 1_000_000),
+            ("make", "buildbox:ellie", 120_000_000_000),
+            ("vim", "nouser", -1),
+        ] {
+            let history = History::new(
+                chrono::Utc::now(),
+                cmd.to_string(),
+                "/home/ellie".to_string(),
+                0,
+                *duration,
+                Some("beep boop".to_string()),
+                Some(hostname.to_string()),
+            );
+            db.save(&history).await.unwrap();
+        }
+
+        let query = |filter_options| {
+            let db = &db;
+            async move {
+                let mut commands: Vec<String> = db
+                    .query_history(&filter_options)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|h| h.command)
+                    .collect();
+                commands.sort();
+                commands
+            }
+        };
+
+        assert_eq!(
+            query(SearchFilter {
+                host: Some("laptop".to_string()),
+                ..SearchFilter::default()
+            })
+            .await,
+            vec!["ls", "sleep 10"]
+        );
+        assert_eq!(
+            query(SearchFilter {
+                host: Some("nouser".to_string()),
+                ..SearchFilter::default()
+            })
+            .await,
+            vec!["vim"]
+        );
+        assert_eq!(
+            query(SearchFilter {
+                user: Some("ellie".to_string()),
+                exclude_host: Some("buildbox".to_string()),
+                ..SearchFilter::default()
+            })
+            .await,
+            vec!["sleep 10"]
+        );
+        assert_eq!(
+            query(SearchFilter {
+                exclude_user: Some("ellie".to_string()),
+                ..SearchFilter::default()
+            })
+            .await,
+            vec!["ls", "vim"]
+        );
+        assert_eq!(
+            query(SearchFilter {
+                min_duration: Some(5_000_000_000),
+                ..SearchFilter::default()
+            })
+            .await,
+            vec!["make", "sleep 10"]
+        );
+        assert_eq!(
+            query(SearchFilter {
+                max_duration: Some(60_000_000_000),
+                ..SearchFilter::default()
+            })
+            .await,
+            vec!["ls", "sleep 10"]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_search_subtree_workspace() {
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        for (cmd, cwd) in &[
+            ("git status", "/home/ellie/atuin"),
+            ("cargo build", "/home/ellie/atuin/atuin-client"),
+            ("cargo test", "/home/ellie/atuin-server"),
+            ("ls", "/home/ellie/100%"),
+            ("cd", "/home/ellie/100%/sub"),
+        ] {
+            let history = History::new(
+                chrono::Utc::now(),
+                cmd.to_string(),
+                cwd.to_string(),
+                0,
+                1,
+                Some("beep boop".to_string()),
+                Some("booop".to_string()),
+            );
+            db.save(&history).await.unwrap();
+        }
+
+        let search = |cwd: &str, git_root: Option<&str>, filter_mode| {
+            let context = Context {
+                hostname: "test:host".to_string(),
+                session: "beepboopiamasession".to_string(),
+                cwd: cwd.to_string(),
+                git_root: git_root.map(ToString::to_string),
+            };
+            let db = &db;
+            async move {
+                let mut commands: Vec<String> = db
+                    .search(
+                        SearchMode::Fuzzy,
+                        filter_mode,
+                        &context,
+                        "",
+                        &SearchFilter::default(),
+                    )
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|h| h.command)
+                    .collect();
+                commands.sort();
+                commands
+            }
+        };
+
+        // a sibling directory sharing the prefix should not match
+        assert_eq!(
+            search("/home/ellie/atuin", None, FilterMode::Subtree).await,
+            vec!["cargo build", "git status"]
+        );
+        assert_eq!(
+            search("/home/ellie/100%", None, FilterMode::Subtree).await,
+            vec!["cd", "ls"]
+        );
+        assert_eq!(
+            search(
+                "/home/ellie/atuin/atuin-client",
+                Some("/home/ellie/atuin"),
+                FilterMode::Workspace
+            )
+            .await,
+            vec!["cargo build", "git status"]
+        );
+        // with no git repository, we fall back to the subtree
+        assert_eq!(
+            search(
+                "/home/ellie/atuin/atuin-client",
+                None,
+                FilterMode::Workspace
+            )
+            .await,
+            vec!["cargo build"]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_command_stats() {
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        for exit in &[0, 0, 1] {
+            let history = History::new(
+                chrono::Utc::now(),
+                "cargo test".to_string(),
+                "/home/ellie".to_string(),
+                *exit,
+                1,
+                Some("beep boop".to_string()),
+                Some("booop".to_string()),
+            );
+            db.save(&history).await.unwrap();
+        }
+        new_history_item(&mut db, "ls").await.unwrap();
+
+        assert_eq!(
+            db.command_stats("cargo test").await.unwrap(),
+            CommandStats {
+                total: 3,
+                success: 2
+            }
+        );
+        assert_eq!(
+            db.command_stats("cargo build").await.unwrap(),
+            CommandStats::default()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_delete() {
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        new_history_item(&mut db, "export TOKEN=hunter2")
+            .await
+            .unwrap();
+        new_history_item(&mut db, "export TOKEN=hunter2")
+            .await
+            .unwrap();
+        new_history_item(&mut db, "ls").await.unwrap();
+
+        let secrets = db
+            .query_history(&SearchFilter {
+                command: Some("export TOKEN=hunter2".to_string()),
+                ..SearchFilter::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(secrets.len(), 2);
+
+        db.delete(&secrets[0]).await.unwrap();
+        assert_eq!(db.history_count().await.unwrap(), 2);
+
+        db.delete_bulk(&secrets).await.unwrap();
+        assert_eq!(db.history_count().await.unwrap(), 1);
+
+        // 3 creates, 2 deletes
+        assert_eq!(db.event_count().await.unwrap(), 5);
+
+        // deleted history should not come back, eg from a sync download
+        assert!(db.exists(&secrets[0]).await.unwrap());
+        db.save_bulk(&secrets).await.unwrap();
+        assert_eq!(db.history_count().await.unwrap(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_local_only() {
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        new_history_item(&mut db, "ls").await.unwrap();
+
+        let secret = History::new(
+            chrono::Utc::now(),
+            "mysql --password=hunter2".to_string(),
+            "/home/ellie".to_string(),
+            0,
+            1,
+            None,
+            None,
+        );
+        db.save_local_only(&secret).await.unwrap();
+
+        assert_eq!(db.history_count().await.unwrap(), 2);
+        assert_eq!(db.sync_count().await.unwrap(), 1);
+
+        let to_sync = db
+            .before(chrono::Utc::now() + chrono::Duration::seconds(1), 10)
+            .await
+            .unwrap();
+        assert_eq!(to_sync.len(), 1);
+        assert_eq!(to_sync[0].command, "ls");
+
+        assert_eq!(db.local_only_ids().await.unwrap(), [secret.id]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_expired() {
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        let now = chrono::Utc::now();
+        let history: Vec<History> = [100, 95, 10, 0]
+            .iter()
+            .map(|days| {
+                History::new(
+                    now - chrono::Duration::days(*days),
+                    format!("{days} days ago"),
+                    "/home/ellie".to_string(),
+                    0,
+                    1,
+                    None,
+                    None,
+                )
+            })
+            .collect();
+        db.save_bulk(&history).await.unwrap();
+        db.pin(&history[0].id).await.unwrap();
+
+        let commands = |h: Vec<History>| h.into_iter().map(|h| h.command).collect::<Vec<_>>();
+        let cutoff = Some(now - chrono::Duration::days(90));
+
+        assert!(db.expired(None, None).await.unwrap().is_empty());
+        assert_eq!(
+            commands(db.expired(cutoff, None).await.unwrap()),
+            ["95 days ago"]
+        );
+        assert_eq!(
+            commands(db.expired(None, Some(1)).await.unwrap()),
+            ["10 days ago", "95 days ago"]
+        );
+        assert_eq!(
+            commands(db.expired(cutoff, Some(3)).await.unwrap()),
+            ["95 days ago"]
+        );
+
+        db.unpin(&history[0].id).await.unwrap();
+        assert_eq!(db.expired(cutoff, None).await.unwrap().len(), 2);
+
+        db.pin(&history[0].id).await.unwrap();
+        assert_eq!(commands(db.pinned().await.unwrap()), ["100 days ago"]);
+        db.delete(&history[0]).await.unwrap();
+        assert!(db.pinned().await.unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_import_watermark() {
+        let db = Sqlite::new("sqlite::memory:").await.unwrap();
+        assert_eq!(db.import_watermark("zsh:/h").await.unwrap(), None);
+
+        let mut watermark = Watermark {
+            offset: 120,
+            timestamp: 0,
+            hash: "abc".to_string(),
+        };
+        db.save_import_watermark("zsh:/h", &watermark)
+            .await
+            .unwrap();
+        watermark.offset = 240;
+        db.save_import_watermark("zsh:/h", &watermark)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.import_watermark("zsh:/h").await.unwrap(),
+            Some(watermark)
+        );
+        assert_eq!(db.import_watermark("bash:/h").await.unwrap(), None);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_search_bench_dupes() {
         let context = Context {
             hostname: "test:host".to_string(),
             session: "beepboopiamasession".to_string(),
             cwd: "/home/ellie".to_string(),
+            git_root: None,
         };
 
         let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
@@ -722,9 +1499,7 @@ mod test {
                 FilterMode::Global,
                 &context,
                 "",
-                None,
-                None,
-                None,
+                &SearchFilter::default(),
             )
             .await
             .unwrap();
@@ -735,6 +1510,10 @@ mod test {
 }
 
 trait SqlBuilderExt {
+    fn filter_mode_condition(&mut self, filter: FilterMode, context: &Context) -> &mut Self;
+
+    fn subtree_condition(&mut self, dir: &str) -> &mut Self;
+
     fn fuzzy_condition<S: ToString, T: ToString>(
         &mut self,
         field: S,
@@ -746,6 +1525,31 @@ trait SqlBuilderExt {
 }
 
 impl SqlBuilderExt for SqlBuilder {
+    fn filter_mode_condition(&mut self, filter: FilterMode, context: &Context) -> &mut Self {
+        match filter {
+            FilterMode::Global => self,
+            FilterMode::Host => self.and_where_eq("hostname", quote(&context.hostname)),
+            FilterMode::Session => self.and_where_eq("session", quote(&context.session)),
+            FilterMode::Directory => self.and_where_eq("cwd", quote(&context.cwd)),
+            FilterMode::Subtree => self.subtree_condition(&context.cwd),
+            // outside of a git repository, this is the same as a subtree search
+            FilterMode::Workspace => {
+                self.subtree_condition(context.git_root.as_deref().unwrap_or(&context.cwd))
+            }
+        }
+    }
+
+    /// match `dir` itself, and anything below it.
+    /// instr is used over LIKE/GLOB so we don't need to escape wildcards in the path
+    fn subtree_condition(&mut self, dir: &str) -> &mut Self {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        self.and_where(format!(
+            "cwd = {} OR instr(cwd, {}) = 1",
+            quote(dir),
+            quote(prefix)
+        ))
+    }
+
     /// adapted from the sql-builder *like functions
     fn fuzzy_condition<S: ToString, T: ToString>(
         &mut self,
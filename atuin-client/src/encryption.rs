@@ -87,22 +87,31 @@ pub fn encrypt(history: &History, key: &secretbox::Key) -> Result<EncryptedHisto
     // serialize with msgpack
     let buf = rmp_serde::to_vec(history)?;
 
-    let nonce = secretbox::gen_nonce();
-
-    let ciphertext = secretbox::seal(&buf, &nonce, key);
-
-    Ok(EncryptedHistory { ciphertext, nonce })
+    Ok(encrypt_bytes(&buf, key))
 }
 
 pub fn decrypt(encrypted_history: &EncryptedHistory, key: &secretbox::Key) -> Result<History> {
-    let plaintext = secretbox::open(&encrypted_history.ciphertext, &encrypted_history.nonce, key)
-        .map_err(|_| eyre!("failed to open secretbox - invalid key?"))?;
+    let plaintext = decrypt_bytes(encrypted_history, key)?;
 
     let history = rmp_serde::from_slice(&plaintext)?;
 
     Ok(history)
 }
 
+// the same as encrypt, for anything that is not a single history entry, such as a backup
+pub fn encrypt_bytes(buf: &[u8], key: &secretbox::Key) -> EncryptedHistory {
+    let nonce = secretbox::gen_nonce();
+
+    let ciphertext = secretbox::seal(buf, &nonce, key);
+
+    EncryptedHistory { ciphertext, nonce }
+}
+
+pub fn decrypt_bytes(encrypted: &EncryptedHistory, key: &secretbox::Key) -> Result<Vec<u8>> {
+    secretbox::open(&encrypted.ciphertext, &encrypted.nonce, key)
+        .map_err(|_| eyre!("failed to open secretbox - invalid key?"))
+}
+
 #[cfg(test)]
 mod test {
     use sodiumoxide::crypto::secretbox;
@@ -39,7 +39,7 @@ impl Event {
             id: uuid_v4(),
             timestamp: chrono::Utc::now(),
             hostname,
-            event_type: EventType::Create,
+            event_type: EventType::Delete,
 
             history_id: history_id.to_string(),
         }
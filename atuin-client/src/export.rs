@@ -0,0 +1,170 @@
+// export history to the shells' own history formats, so it can be taken back out of atuin
+//
+// Each writer takes history newest first, as it comes out of the database, and
+// writes it oldest first, as the shells do. Only what the format has room for
+// is kept: none of them have the directory or exit code.
+
+use std::io::{Result, Write};
+
+use crate::history::History;
+
+/// zsh's extended history, `: <start>:<duration in seconds>;<command>`
+pub fn write_zsh(w: &mut impl Write, h: &[History]) -> Result<()> {
+    for h in h.iter().rev() {
+        let duration = h.duration.max(0) / 1_000_000_000;
+        // zsh ends every line but the last of a multi-line command with a backslash
+        let command = h.command.replace('\n', "\\\n");
+        writeln!(w, ": {}:{};{}", h.timestamp.timestamp(), duration, command)?;
+    }
+
+    Ok(())
+}
+
+/// bash's history with timestamps, as written with `HISTTIMEFORMAT` set.
+/// Every line is a command, so multi-line commands are left out, as are
+/// commands that would be read back as a timestamp
+pub fn write_bash(w: &mut impl Write, h: &[History]) -> Result<()> {
+    for h in h.iter().rev() {
+        if h.command.contains('\n') || is_bash_timestamp(&h.command) {
+            continue;
+        }
+
+        writeln!(w, "#{}", h.timestamp.timestamp())?;
+        writeln!(w, "{}", h.command)?;
+    }
+
+    Ok(())
+}
+
+fn is_bash_timestamp(command: &str) -> bool {
+    command
+        .strip_prefix('#')
+        .and_then(|s| s.parse::<i64>().ok())
+        .is_some()
+}
+
+/// fish's YAML-like history. fish also lists the paths each command used under
+/// `paths`, which atuin does not know, so that is left out
+pub fn write_fish(w: &mut impl Write, h: &[History]) -> Result<()> {
+    for h in h.iter().rev() {
+        let command = h.command.replace('\\', "\\\\").replace('\n', "\\n");
+        writeln!(w, "- cmd: {command}")?;
+        writeln!(w, "  when: {}", h.timestamp.timestamp())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use chrono::{TimeZone, Utc};
+    use itertools::assert_equal;
+
+    use atuin_common::utils::uuid_v4;
+
+    use super::*;
+    use crate::import::{bash::Bash, fish::Fish, tests::TestLoader, zsh::Zsh, Importer};
+
+    // newest first, like the database
+    fn history(commands: &[&str]) -> Vec<History> {
+        commands
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, command)| {
+                History::new(
+                    Utc.timestamp(1_673_000_000 + i as i64 * 60, 0),
+                    (*command).to_string(),
+                    "/".to_string(),
+                    0,
+                    (i as i64 + 1) * 1_000_000_000,
+                    None,
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    async fn round_trip<I: Importer>(
+        write: fn(&mut Vec<u8>, &[History]) -> Result<()>,
+        history: &[History],
+    ) -> Vec<History> {
+        let mut bytes = Vec::new();
+        write(&mut bytes, history).unwrap();
+
+        let path: PathBuf = std::env::temp_dir().join(format!("atuin-export-{}", uuid_v4()));
+        std::fs::write(&path, bytes).unwrap();
+        let importer = I::new(path.clone()).await.unwrap();
+        let mut loader = TestLoader::default();
+        importer.load(&mut loader).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        loader.buf
+    }
+
+    fn assert_same(exported: &[History], imported: &[History]) {
+        assert_equal(
+            imported
+                .iter()
+                .map(|h| (h.command.as_str(), h.timestamp.timestamp())),
+            exported
+                .iter()
+                .rev()
+                .map(|h| (h.command.as_str(), h.timestamp.timestamp())),
+        );
+    }
+
+    #[tokio::test]
+    async fn zsh() {
+        let history = history(&[
+            "ls -la",
+            "cd /tmp",
+            "for f in *\ndo\n  echo $f\ndone",
+            "echo 'a;b:c'",
+        ]);
+        let mut imported = round_trip::<Zsh>(write_zsh, &history).await;
+
+        // the zsh importer keeps the backslash that continues each line
+        assert_eq!(imported[2].command, "for f in *\\\ndo\\\n  echo $f\\\ndone");
+        imported[2].command = imported[2].command.replace("\\\n", "\n");
+        assert_same(&history, &imported);
+        assert_equal(
+            imported.iter().map(|h| h.duration),
+            [1_000_000_000, 2_000_000_000, 3_000_000_000, 4_000_000_000],
+        );
+
+        let mut bytes = Vec::new();
+        write_zsh(&mut bytes, &history[..1]).unwrap();
+        assert_eq!(bytes, b": 1673000180:4;echo 'a;b:c'\n");
+    }
+
+    #[tokio::test]
+    async fn bash() {
+        let history = history(&[
+            "ls -la",
+            "#notatimestamp",
+            "for f in *\ndo\n  echo $f\ndone",
+            "#1673000000",
+            "git commit -m 'x'",
+        ]);
+        let imported = round_trip::<Bash>(write_bash, &history).await;
+
+        // the multi-line command and the one that looks like a timestamp are left out
+        let kept: Vec<History> = history
+            .iter()
+            .filter(|h| !h.command.contains('\n') && h.command != "#1673000000")
+            .cloned()
+            .collect();
+        assert_same(&kept, &imported);
+    }
+
+    #[tokio::test]
+    async fn fish() {
+        let history = history(&["ls", "for f in *\n  echo $f\nend", r"echo 'a\b'"]);
+        let imported = round_trip::<Fish>(write_fish, &history).await;
+
+        assert_same(&history, &imported);
+    }
+}
@@ -0,0 +1,154 @@
+// import history from another atuin database, such as one from a machine that never synced
+//
+// The entries keep their ids, so anything already in this database (because it
+// was imported before, or came in through sync) is skipped when it is saved.
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use eyre::{bail, Result};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePool, SqliteRow},
+    Pool, Row,
+};
+
+use super::{explicit_histpath, hash_bytes, Importer, Loader, Watermark};
+use crate::history::History;
+
+#[derive(Debug)]
+pub struct AtuinDb {
+    history: Vec<History>,
+}
+
+/// Read db at given file, return vector of entries.
+async fn hist_from_db(dbpath: PathBuf) -> Result<Vec<History>> {
+    // read only, so the other database is not migrated or otherwise touched
+    let opts = SqliteConnectOptions::new().filename(dbpath).read_only(true);
+    let pool = SqlitePool::connect_with(opts).await?;
+    hist_from_db_conn(pool).await
+}
+
+async fn hist_from_db_conn(pool: Pool<sqlx::Sqlite>) -> Result<Vec<History>> {
+    let query = "select id, timestamp, duration, exit, command, cwd, session, hostname from history order by timestamp";
+    let history = sqlx::query(query)
+        .map(|row: SqliteRow| History {
+            id: row.get("id"),
+            timestamp: Utc.timestamp_nanos(row.get("timestamp")),
+            duration: row.get("duration"),
+            exit: row.get("exit"),
+            command: row.get("command"),
+            cwd: row.get("cwd"),
+            session: row.get("session"),
+            hostname: row.get("hostname"),
+        })
+        .fetch_all(&pool)
+        .await?;
+    Ok(history)
+}
+
+#[async_trait]
+impl Importer for AtuinDb {
+    const NAME: &'static str = "atuin_db";
+
+    fn locate(path: Option<PathBuf>) -> Result<PathBuf> {
+        // there is no sensible default, it would just be our own database
+        let Some(path) = path else {
+            bail!("Importing from another atuin database needs the path to its history.db");
+        };
+        explicit_histpath(path)
+    }
+
+    async fn new(path: PathBuf) -> Result<Self> {
+        let history = hist_from_db(path).await?;
+        Ok(Self { history })
+    }
+
+    async fn entries(&mut self) -> Result<usize> {
+        Ok(self.history.len())
+    }
+
+    async fn load(self, h: &mut impl Loader) -> Result<()> {
+        for i in self.history {
+            h.push(i).await?;
+        }
+        Ok(())
+    }
+
+    // the ids are uuids, so go by time instead
+    fn watermark(&self) -> Watermark {
+        self.history
+            .iter()
+            .max_by_key(|h| h.timestamp)
+            .map_or_else(Watermark::default, |h| Watermark {
+                offset: 0,
+                timestamp: h.timestamp.timestamp_nanos(),
+                hash: hash_bytes(h.id.as_bytes()),
+            })
+    }
+
+    async fn resume(&mut self, watermark: &Watermark) -> Result<Option<usize>> {
+        if watermark.timestamp != 0
+            && !self
+                .history
+                .iter()
+                .any(|h| hash_bytes(h.id.as_bytes()) == watermark.hash)
+        {
+            return Ok(None);
+        }
+
+        let before = self.history.len();
+        self.history
+            .retain(|h| h.timestamp.timestamp_nanos() > watermark.timestamp);
+        Ok(Some(before - self.history.len()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::import::tests::TestLoader;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_import() {
+        let pool: SqlitePool = SqlitePoolOptions::new()
+            .min_connections(2)
+            .connect(":memory:")
+            .await
+            .unwrap();
+
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        sqlx::query(
+            "insert into history values
+                ('b3c1', 1673000005000000000, 2000000, 1, 'cargo build', '/src', 'sess', 'old:me'),
+                ('a9f0', 1673000000000000000, 5000, 0, 'cd /src', '/', 'sess', 'old:me')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let history = hist_from_db_conn(pool).await.unwrap();
+        let mut atuin = AtuinDb { history };
+        assert_eq!(atuin.entries().await.unwrap(), 2);
+
+        let mut loader = TestLoader::default();
+        atuin.load(&mut loader).await.unwrap();
+        let history = loader.buf;
+
+        // every field is kept as it was, including the id
+        assert_eq!(history[0].id, "a9f0");
+        assert_eq!(history[0].command, "cd /src");
+        assert_eq!(history[1].id, "b3c1");
+        assert_eq!(
+            history[1].timestamp.timestamp_nanos(),
+            1_673_000_005_000_000_000
+        );
+        assert_eq!(history[1].duration, 2_000_000);
+        assert_eq!(history[1].exit, 1);
+        assert_eq!(history[1].cwd, "/src");
+        assert_eq!(history[1].session, "sess");
+        assert_eq!(history[1].hostname, "old:me");
+    }
+}
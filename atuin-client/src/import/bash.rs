@@ -6,7 +6,10 @@ use directories::UserDirs;
 use eyre::{eyre, Result};
 use itertools::Itertools;
 
-use super::{get_histpath, unix_byte_lines, Importer, Loader};
+use super::{
+    file_watermark, get_histpath, invalid_lines, resume_file, unix_byte_lines, Importer, Loader,
+    Watermark,
+};
 use crate::history::History;
 
 #[derive(Debug)]
@@ -25,9 +28,12 @@ fn default_histpath() -> Result<PathBuf> {
 impl Importer for Bash {
     const NAME: &'static str = "bash";
 
-    async fn new() -> Result<Self> {
+    fn locate(path: Option<PathBuf>) -> Result<PathBuf> {
+        get_histpath(path, default_histpath)
+    }
+
+    async fn new(path: PathBuf) -> Result<Self> {
         let mut bytes = Vec::new();
-        let path = get_histpath(default_histpath)?;
         let mut f = File::open(path)?;
         f.read_to_end(&mut bytes)?;
         Ok(Self { bytes })
@@ -87,6 +93,18 @@ impl Importer for Bash {
 
         Ok(())
     }
+
+    fn watermark(&self) -> Watermark {
+        file_watermark(&self.bytes)
+    }
+
+    async fn resume(&mut self, watermark: &Watermark) -> Result<Option<usize>> {
+        resume_file(&mut self.bytes, watermark, |bytes| Self { bytes }).await
+    }
+
+    fn invalid(&self) -> usize {
+        invalid_lines(&self.bytes)
+    }
 }
 
 #[derive(Debug, Clone)]
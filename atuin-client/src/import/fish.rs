@@ -8,7 +8,10 @@ use chrono::{prelude::*, Utc};
 use directories::BaseDirs;
 use eyre::{eyre, Result};
 
-use super::{unix_byte_lines, Importer, Loader};
+use super::{
+    explicit_histpath, file_watermark, invalid_lines, resume_file, unix_byte_lines, Importer,
+    Loader, Watermark,
+};
 use crate::history::History;
 
 #[derive(Debug)]
@@ -44,9 +47,16 @@ fn default_histpath() -> Result<PathBuf> {
 impl Importer for Fish {
     const NAME: &'static str = "fish";
 
-    async fn new() -> Result<Self> {
+    fn locate(path: Option<PathBuf>) -> Result<PathBuf> {
+        match path {
+            Some(p) => explicit_histpath(p),
+            None => default_histpath(),
+        }
+    }
+
+    async fn new(path: PathBuf) -> Result<Self> {
         let mut bytes = Vec::new();
-        let mut f = File::open(default_histpath()?)?;
+        let mut f = File::open(path)?;
         f.read_to_end(&mut bytes)?;
         Ok(Self { bytes })
     }
@@ -121,6 +131,18 @@ impl Importer for Fish {
 
         Ok(())
     }
+
+    fn watermark(&self) -> Watermark {
+        file_watermark(&self.bytes)
+    }
+
+    async fn resume(&mut self, watermark: &Watermark) -> Result<Option<usize>> {
+        resume_file(&mut self.bytes, watermark, |bytes| Self { bytes }).await
+    }
+
+    fn invalid(&self) -> usize {
+        invalid_lines(&self.bytes)
+    }
 }
 
 #[cfg(test)]
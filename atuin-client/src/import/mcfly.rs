@@ -0,0 +1,183 @@
+// import old shell history from McFly's sqlite database
+//
+// McFly keeps one row per command run, in this table:
+//
+// CREATE TABLE commands(
+//     id INTEGER PRIMARY KEY AUTOINCREMENT,
+//     cmd TEXT NOT NULL,
+//     cmd_tpl TEXT,
+//     session_id TEXT NOT NULL,
+//     when_run INTEGER NOT NULL,
+//     exit_code INTEGER NOT NULL,
+//     selected INTEGER NOT NULL,
+//     dir TEXT,
+//     old_dir TEXT
+// );
+//
+// when_run is in seconds since the epoch. There is no duration or hostname,
+// McFly only ever sees the machine it runs on.
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use directories::{ProjectDirs, UserDirs};
+use eyre::{eyre, Result};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePool},
+    Pool,
+};
+
+use super::{explicit_histpath, resume_rows, row_watermark, Importer, Loader, Watermark};
+use crate::history::History;
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct McflyEntry {
+    pub id: i64,
+    pub cmd: String,
+    pub session_id: String,
+    pub when_run: i64,
+    pub exit_code: i64,
+    pub dir: Option<String>,
+}
+
+impl From<McflyEntry> for History {
+    fn from(entry: McflyEntry) -> Self {
+        History::new(
+            Utc.timestamp(entry.when_run, 0),
+            entry.cmd,
+            entry.dir.unwrap_or_else(|| "unknown".into()),
+            entry.exit_code,
+            -1,
+            Some(entry.session_id),
+            None,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Mcfly {
+    entries: Vec<McflyEntry>,
+}
+
+/// Read db at given file, return vector of entries.
+async fn hist_from_db(dbpath: PathBuf) -> Result<Vec<McflyEntry>> {
+    let opts = SqliteConnectOptions::new().filename(dbpath).read_only(true);
+    let pool = SqlitePool::connect_with(opts).await?;
+    hist_from_db_conn(pool).await
+}
+
+async fn hist_from_db_conn(pool: Pool<sqlx::Sqlite>) -> Result<Vec<McflyEntry>> {
+    let query =
+        "select id, cmd, session_id, when_run, exit_code, dir from commands order by when_run, id";
+    let entries = sqlx::query_as::<_, McflyEntry>(query)
+        .fetch_all(&pool)
+        .await?;
+    Ok(entries)
+}
+
+impl Mcfly {
+    pub fn histpath() -> Result<PathBuf> {
+        // McFly still uses ~/.mcfly if it exists, from before it followed the platform's conventions
+        let user_dirs = UserDirs::new().ok_or_else(|| eyre!("could not find user directories"))?;
+        let legacy = user_dirs.home_dir().join(".mcfly").join("history.db");
+        if legacy.exists() {
+            return Ok(legacy);
+        }
+
+        let dirs = ProjectDirs::from("", "", "McFly")
+            .ok_or_else(|| eyre!("could not determine data directory"))?;
+        let histpath = dirs.data_dir().join("history.db");
+        if histpath.exists() {
+            Ok(histpath)
+        } else {
+            Err(eyre!("Could not find history file {:?}", histpath))
+        }
+    }
+}
+
+#[async_trait]
+impl Importer for Mcfly {
+    const NAME: &'static str = "mcfly";
+
+    fn locate(path: Option<PathBuf>) -> Result<PathBuf> {
+        match path {
+            Some(p) => explicit_histpath(p),
+            None => Mcfly::histpath(),
+        }
+    }
+
+    async fn new(path: PathBuf) -> Result<Self> {
+        let entries = hist_from_db(path).await?;
+        Ok(Self { entries })
+    }
+
+    async fn entries(&mut self) -> Result<usize> {
+        Ok(self.entries.len())
+    }
+
+    async fn load(self, h: &mut impl Loader) -> Result<()> {
+        for i in self.entries {
+            h.push(i.into()).await?;
+        }
+        Ok(())
+    }
+
+    fn watermark(&self) -> Watermark {
+        row_watermark(self.entries.iter().map(|e| (e.id, e.cmd.as_bytes())))
+    }
+
+    async fn resume(&mut self, watermark: &Watermark) -> Result<Option<usize>> {
+        Ok(resume_rows(&mut self.entries, watermark, |e| {
+            (e.id, e.cmd.as_bytes())
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::import::tests::TestLoader;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_import() {
+        let pool: SqlitePool = SqlitePoolOptions::new()
+            .min_connections(2)
+            .connect(":memory:")
+            .await
+            .unwrap();
+
+        let db_sql = r#"
+        BEGIN TRANSACTION;
+        CREATE TABLE commands(id INTEGER PRIMARY KEY AUTOINCREMENT, cmd TEXT NOT NULL, cmd_tpl TEXT, session_id TEXT NOT NULL, when_run INTEGER NOT NULL, exit_code INTEGER NOT NULL, selected INTEGER NOT NULL, dir TEXT, old_dir TEXT);
+        INSERT INTO commands VALUES(1,'make test','make test','2a6fd1c3',1651497930,2,0,'/home/mc/proj',NULL);
+        INSERT INTO commands VALUES(2,'git status','git status','2a6fd1c3',1651497918,0,1,'/home/mc/proj','/home/mc');
+        INSERT INTO commands VALUES(3,'ls','ls','9b01ee47',1651497940,0,0,NULL,NULL);
+        COMMIT; "#;
+
+        sqlx::query(db_sql).execute(&pool).await.unwrap();
+
+        let entries = hist_from_db_conn(pool).await.unwrap();
+        let mut mcfly = Mcfly { entries };
+        assert_eq!(mcfly.entries().await.unwrap(), 3);
+
+        let mut loader = TestLoader::default();
+        mcfly.load(&mut loader).await.unwrap();
+        let history = loader.buf;
+
+        // ordered by when they were run
+        assert_eq!(history[0].command, "git status");
+        assert_eq!(history[0].timestamp.timestamp(), 1_651_497_918);
+        assert_eq!(history[0].cwd, "/home/mc/proj");
+        assert_eq!(history[0].session, "2a6fd1c3");
+
+        assert_eq!(history[1].command, "make test");
+        assert_eq!(history[1].exit, 2);
+        assert_eq!(history[1].duration, -1);
+
+        assert_eq!(history[2].cwd, "unknown");
+        assert_eq!(history[2].session, "9b01ee47");
+    }
+}
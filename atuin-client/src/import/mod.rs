@@ -1,13 +1,18 @@
-use std::path::PathBuf;
+use std::{convert::TryFrom, path::PathBuf};
 
 use async_trait::async_trait;
 use eyre::{bail, Result};
 use memchr::Memchr;
+use sha2::{Digest, Sha256};
 
 use crate::history::History;
 
+pub mod atuin_db;
 pub mod bash;
 pub mod fish;
+pub mod mcfly;
+pub mod nu;
+pub mod nu_histdb;
 pub mod resh;
 pub mod zsh;
 pub mod zsh_histdb;
@@ -15,9 +20,34 @@ pub mod zsh_histdb;
 #[async_trait]
 pub trait Importer: Sized {
     const NAME: &'static str;
-    async fn new() -> Result<Self>;
+    /// The history to read, `path` if one was given, or the shell's usual history otherwise
+    fn locate(path: Option<PathBuf>) -> Result<PathBuf>;
+    async fn new(path: PathBuf) -> Result<Self>;
     async fn entries(&mut self) -> Result<usize>;
     async fn load(self, loader: &mut impl Loader) -> Result<()>;
+
+    /// How far this import will get, for the next one to resume from.
+    /// This has to be taken before calling `resume`
+    fn watermark(&self) -> Watermark;
+    /// Leave out everything imported before, up to `watermark`, returning how many entries
+    /// that was, or `None` if the history has changed so much that it cannot be found
+    async fn resume(&mut self, watermark: &Watermark) -> Result<Option<usize>>;
+    /// How many entries will be left out because they are not valid UTF-8
+    fn invalid(&self) -> usize {
+        0
+    }
+}
+
+/// How much of a history has already been imported, stored per source so
+/// importing it again only picks up what is new
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Watermark {
+    /// The end of the last complete line for history files, or the last row id for databases
+    pub offset: i64,
+    /// The newest entry, in nanoseconds, for databases without row ids
+    pub timestamp: i64,
+    /// A hash of the imported content just before `offset`, to check it is still there
+    pub hash: String,
 }
 
 #[async_trait]
@@ -61,17 +91,118 @@ fn count_lines(input: &[u8]) -> usize {
     unix_byte_lines(input).count()
 }
 
-fn get_histpath<D>(def: D) -> Result<PathBuf>
+// enough to be sure it is the same history, while cheap enough to look for on every line
+const WATERMARK_TAIL: usize = 4096;
+
+fn hash_bytes(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// The watermark for a history file, just after its last complete line
+fn file_watermark(bytes: &[u8]) -> Watermark {
+    let offset = memchr::memrchr(b'\n', bytes).map_or(0, |i| i + 1);
+
+    Watermark {
+        offset: offset as i64,
+        timestamp: 0,
+        hash: hash_bytes(&bytes[offset.saturating_sub(WATERMARK_TAIL)..offset]),
+    }
+}
+
+/// Split off the start of a history file that was imported up to `watermark`.
+/// Usually the file has only grown since, but shells trim the oldest lines,
+/// so if it is not where it was, the imported part is looked for at every line.
+fn split_imported(bytes: &mut Vec<u8>, watermark: &Watermark) -> Option<Vec<u8>> {
+    let offset = usize::try_from(watermark.offset).ok()?;
+    let tail = offset.min(WATERMARK_TAIL);
+    let imported_up_to =
+        |end: usize| end >= tail && hash_bytes(&bytes[end - tail..end]) == watermark.hash;
+
+    let end = if offset <= bytes.len() && imported_up_to(offset) {
+        offset
+    } else {
+        memchr::memrchr_iter(b'\n', bytes)
+            .map(|i| i + 1)
+            .find(|&end| imported_up_to(end))?
+    };
+
+    let rest = bytes.split_off(end);
+    Some(std::mem::replace(bytes, rest))
+}
+
+/// Resume an importer that reads a whole history file into memory, counting the
+/// entries in the part split off as already imported
+async fn resume_file<I: Importer>(
+    bytes: &mut Vec<u8>,
+    watermark: &Watermark,
+    from_bytes: impl FnOnce(Vec<u8>) -> I + Send,
+) -> Result<Option<usize>> {
+    match split_imported(bytes, watermark) {
+        Some(imported) => from_bytes(imported).entries().await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// The watermark for a history database, at the row with the highest id
+fn row_watermark<'a>(rows: impl Iterator<Item = (i64, &'a [u8])>) -> Watermark {
+    rows.max_by_key(|(id, _)| *id)
+        .map_or_else(Watermark::default, |(id, command)| Watermark {
+            offset: id,
+            timestamp: 0,
+            hash: hash_bytes(command),
+        })
+}
+
+/// Leave out the rows of a history database up to the one at `watermark`, as long as
+/// that row still holds the same command
+fn resume_rows<T>(
+    rows: &mut Vec<T>,
+    watermark: &Watermark,
+    row: impl Fn(&T) -> (i64, &[u8]),
+) -> Option<usize> {
+    if watermark.offset != 0 {
+        let (_, command) = rows
+            .iter()
+            .map(&row)
+            .find(|(id, _)| *id == watermark.offset)?;
+        if hash_bytes(command) != watermark.hash {
+            return None;
+        }
+    }
+
+    let before = rows.len();
+    rows.retain(|r| row(r).0 > watermark.offset);
+    Some(before - rows.len())
+}
+
+fn invalid_lines(input: &[u8]) -> usize {
+    unix_byte_lines(input)
+        .filter(|line| std::str::from_utf8(line).is_err())
+        .count()
+}
+
+fn get_histpath<D>(path: Option<PathBuf>, def: D) -> Result<PathBuf>
 where
     D: FnOnce() -> Result<PathBuf>,
 {
-    if let Ok(p) = std::env::var("HISTFILE") {
+    if let Some(p) = path {
+        explicit_histpath(p)
+    } else if let Ok(p) = std::env::var("HISTFILE") {
         is_file(PathBuf::from(p))
     } else {
         is_file(def()?)
     }
 }
 
+/// A path given on the command line, so the environment is no help if it is missing
+fn explicit_histpath(p: PathBuf) -> Result<PathBuf> {
+    if p.is_file() {
+        Ok(p)
+    } else {
+        bail!("Could not find history file {:?}", p)
+    }
+}
+
 fn is_file(p: PathBuf) -> Result<PathBuf> {
     if p.is_file() {
         Ok(p)
@@ -81,7 +212,7 @@ fn is_file(p: PathBuf) -> Result<PathBuf> {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
     #[derive(Default)]
@@ -96,4 +227,55 @@ mod tests {
             Ok(())
         }
     }
+
+    #[test]
+    fn split_at_watermark() {
+        let mut bytes = b"ls\ncd /\npwd\n".to_vec();
+        let watermark = file_watermark(&bytes);
+        assert_eq!(watermark.offset, 12);
+
+        // appended to
+        bytes.extend_from_slice(b"echo new\npartial");
+        let imported = split_imported(&mut bytes, &watermark).unwrap();
+        assert_eq!(imported, b"ls\ncd /\npwd\n");
+        assert_eq!(bytes, b"echo new\npartial");
+
+        // the partial last line is left for next time
+        let mut bytes = b"ls\ncd /\npwd\necho new\npartial".to_vec();
+        assert_eq!(file_watermark(&bytes).offset, 21);
+
+        // rewritten
+        let mut changed = b"ls\ncd ~\npwd\necho new\n".to_vec();
+        assert!(split_imported(&mut changed, &watermark).is_none());
+
+        // trimmed from the start
+        let mut long = (0..1000)
+            .map(|i| format!("echo {i}\n"))
+            .collect::<String>()
+            .into_bytes();
+        let watermark = file_watermark(&long);
+        long.drain(..4000);
+        long.extend_from_slice(b"echo new\n");
+        let imported = split_imported(&mut long, &watermark).unwrap();
+        assert!(imported.ends_with(b"echo 999\n"));
+        assert_eq!(long, b"echo new\n");
+
+        assert_eq!(invalid_lines(&bytes), 0);
+        bytes.extend_from_slice(b"\xff\n");
+        assert_eq!(invalid_lines(&bytes), 1);
+    }
+
+    #[test]
+    fn explicit_histpath() {
+        let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        assert_eq!(
+            get_histpath(Some(manifest.clone()), || unreachable!()).unwrap(),
+            manifest
+        );
+
+        let missing = manifest.with_file_name("no_such_history");
+        let err = get_histpath(Some(missing), || unreachable!()).unwrap_err();
+        assert!(err.to_string().contains("no_such_history"));
+        assert!(!err.to_string().contains("HISTFILE"));
+    }
 }
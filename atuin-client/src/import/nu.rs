@@ -0,0 +1,133 @@
+// import old shell history from nushell's plaintext history.txt
+// the sqlite format is handled by nu_histdb
+
+use std::{fs::File, io::Read, path::PathBuf};
+
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use directories::BaseDirs;
+use eyre::{eyre, Result};
+
+use super::{
+    explicit_histpath, file_watermark, invalid_lines, resume_file, unix_byte_lines, Importer,
+    Loader, Watermark,
+};
+use crate::history::History;
+
+// reedline writes multi-line commands on a single line, with this in place of each newline
+const NEWLINE_ESCAPE: &str = "<\\n>";
+
+#[derive(Debug)]
+pub struct Nu {
+    bytes: Vec<u8>,
+}
+
+fn default_histpath() -> Result<PathBuf> {
+    let base = BaseDirs::new().ok_or_else(|| eyre!("could not determine config directory"))?;
+
+    Ok(base.config_dir().join("nushell").join("history.txt"))
+}
+
+#[async_trait]
+impl Importer for Nu {
+    const NAME: &'static str = "nu";
+
+    fn locate(path: Option<PathBuf>) -> Result<PathBuf> {
+        match path {
+            Some(p) => explicit_histpath(p),
+            None => default_histpath(),
+        }
+    }
+
+    async fn new(path: PathBuf) -> Result<Self> {
+        let mut bytes = Vec::new();
+        let mut f = File::open(path)?;
+        f.read_to_end(&mut bytes)?;
+        Ok(Self { bytes })
+    }
+
+    async fn entries(&mut self) -> Result<usize> {
+        let count = unix_byte_lines(&self.bytes)
+            .filter(|b| !b.is_empty())
+            .count();
+        Ok(count)
+    }
+
+    async fn load(self, h: &mut impl Loader) -> Result<()> {
+        let commands: Vec<&str> = unix_byte_lines(&self.bytes)
+            .filter_map(|b| std::str::from_utf8(b).ok()) // we can skip past things like invalid utf8
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        // there are no timestamps in this format, so space the commands out
+        // up until now, to preserve the ordering
+        let timestamp_increment = Duration::seconds(1);
+        let mut next_timestamp = Utc::now() - timestamp_increment * commands.len() as i32;
+
+        for c in commands {
+            let entry = History::new(
+                next_timestamp,
+                c.replace(NEWLINE_ESCAPE, "\n"),
+                "unknown".into(),
+                -1,
+                -1,
+                None,
+                None,
+            );
+            h.push(entry).await?;
+            next_timestamp += timestamp_increment;
+        }
+
+        Ok(())
+    }
+
+    fn watermark(&self) -> Watermark {
+        file_watermark(&self.bytes)
+    }
+
+    async fn resume(&mut self, watermark: &Watermark) -> Result<Option<usize>> {
+        resume_file(&mut self.bytes, watermark, |bytes| Self { bytes }).await
+    }
+
+    fn invalid(&self) -> usize {
+        invalid_lines(&self.bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::assert_equal;
+
+    use crate::import::{tests::TestLoader, Importer};
+
+    use super::Nu;
+
+    #[tokio::test]
+    async fn parse() {
+        let bytes = b"ls | where size > 1mb
+def greet [name] {<\\n>  $\"hello ($name)\"<\\n>}
+
+cd ~/code
+"
+        .to_vec();
+
+        let mut nu = Nu { bytes };
+        assert_eq!(nu.entries().await.unwrap(), 3);
+
+        let mut loader = TestLoader::default();
+        nu.load(&mut loader).await.unwrap();
+
+        assert_equal(
+            loader.buf.iter().map(|h| h.command.as_str()),
+            [
+                "ls | where size > 1mb",
+                "def greet [name] {\n  $\"hello ($name)\"\n}",
+                "cd ~/code",
+            ],
+        );
+        assert!(loader
+            .buf
+            .windows(2)
+            .all(|w| w[0].timestamp < w[1].timestamp));
+    }
+}
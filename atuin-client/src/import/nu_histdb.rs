@@ -0,0 +1,203 @@
+// import old shell history from nushell's sqlite history.sqlite3
+//
+// This is reedline's schema:
+//
+// create table if not exists history (
+//     id integer primary key autoincrement,
+//     command_line text not null,
+//     start_timestamp integer,
+//     session_id integer,
+//     hostname text,
+//     cwd text,
+//     duration_ms integer,
+//     exit_status integer,
+//     more_info text
+// ) strict;
+//
+// start_timestamp is in milliseconds since the epoch. Any of the nullable
+// columns may be missing, depending on what the shell knew at the time.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use directories::BaseDirs;
+use eyre::{eyre, Result};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePool},
+    Pool,
+};
+
+use atuin_common::utils::uuid_v4;
+
+use super::{explicit_histpath, resume_rows, row_watermark, Importer, Loader, Watermark};
+use crate::history::History;
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct NuHistDbEntry {
+    pub id: i64,
+    pub command_line: String,
+    pub start_timestamp: Option<i64>,
+    pub session_id: Option<i64>,
+    pub hostname: Option<String>,
+    pub cwd: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub exit_status: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct NuHistDb {
+    histdb: Vec<NuHistDbEntry>,
+}
+
+/// Read db at given file, return vector of entries.
+async fn hist_from_db(dbpath: PathBuf) -> Result<Vec<NuHistDbEntry>> {
+    let opts = SqliteConnectOptions::new().filename(dbpath).read_only(true);
+    let pool = SqlitePool::connect_with(opts).await?;
+    hist_from_db_conn(pool).await
+}
+
+async fn hist_from_db_conn(pool: Pool<sqlx::Sqlite>) -> Result<Vec<NuHistDbEntry>> {
+    let query = "select id, command_line, start_timestamp, session_id, hostname, cwd, duration_ms, exit_status from history order by id";
+    let histdb_vec: Vec<NuHistDbEntry> = sqlx::query_as::<_, NuHistDbEntry>(query)
+        .fetch_all(&pool)
+        .await?;
+    Ok(histdb_vec)
+}
+
+impl NuHistDb {
+    pub fn histpath_candidate() -> Result<PathBuf> {
+        let base = BaseDirs::new().ok_or_else(|| eyre!("could not determine config directory"))?;
+
+        Ok(base.config_dir().join("nushell").join("history.sqlite3"))
+    }
+
+    pub fn histpath() -> Result<PathBuf> {
+        let histdb_path = NuHistDb::histpath_candidate()?;
+        if histdb_path.exists() {
+            Ok(histdb_path)
+        } else {
+            Err(eyre!("Could not find history file {:?}", histdb_path))
+        }
+    }
+}
+
+#[async_trait]
+impl Importer for NuHistDb {
+    const NAME: &'static str = "nu_histdb";
+
+    fn locate(path: Option<PathBuf>) -> Result<PathBuf> {
+        match path {
+            Some(p) => explicit_histpath(p),
+            None => NuHistDb::histpath(),
+        }
+    }
+
+    async fn new(path: PathBuf) -> Result<Self> {
+        let histdb = hist_from_db(path).await?;
+        Ok(Self { histdb })
+    }
+
+    async fn entries(&mut self) -> Result<usize> {
+        Ok(self.histdb.len())
+    }
+
+    async fn load(self, h: &mut impl Loader) -> Result<()> {
+        let username = whoami::username();
+        let mut last_timestamp = None;
+
+        // nushell numbers its sessions, atuin uses uuids
+        let mut sessions = HashMap::new();
+
+        for entry in self.histdb {
+            // entries without a start time were run around the same time as the ones before them
+            let timestamp = entry
+                .start_timestamp
+                .map(|ms| Utc.timestamp_millis(ms))
+                .or(last_timestamp)
+                .unwrap_or_else(Utc::now);
+            last_timestamp = Some(timestamp);
+
+            let session = entry
+                .session_id
+                .map(|id| sessions.entry(id).or_insert_with(uuid_v4).clone());
+
+            h.push(History::new(
+                timestamp,
+                entry.command_line,
+                entry.cwd.unwrap_or_else(|| "unknown".into()),
+                entry.exit_status.unwrap_or(-1),
+                entry.duration_ms.map_or(-1, |ms| ms * 1_000_000),
+                Some(session.unwrap_or_else(uuid_v4)),
+                entry.hostname.map(|host| format!("{host}:{username}")),
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    fn watermark(&self) -> Watermark {
+        row_watermark(
+            self.histdb
+                .iter()
+                .map(|e| (e.id, e.command_line.as_bytes())),
+        )
+    }
+
+    async fn resume(&mut self, watermark: &Watermark) -> Result<Option<usize>> {
+        Ok(resume_rows(&mut self.histdb, watermark, |e| {
+            (e.id, e.command_line.as_bytes())
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::import::tests::TestLoader;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_import() {
+        let pool: SqlitePool = SqlitePoolOptions::new()
+            .min_connections(2)
+            .connect(":memory:")
+            .await
+            .unwrap();
+
+        let db_sql = r#"
+        BEGIN TRANSACTION;
+        CREATE TABLE history (id integer primary key autoincrement, command_line text not null, start_timestamp integer, session_id integer, hostname text, cwd text, duration_ms integer, exit_status integer, more_info text) strict;
+        INSERT INTO history VALUES(1,'ls | length',1673000000000,7,'laptop','/home/nu',12,0,NULL);
+        INSERT INTO history VALUES(2,'cargo test',1673000005500,7,'laptop','/home/nu/code',30250,101,NULL);
+        INSERT INTO history VALUES(3,'exit',NULL,8,NULL,NULL,NULL,NULL,NULL);
+        COMMIT; "#;
+
+        sqlx::query(db_sql).execute(&pool).await.unwrap();
+
+        let histdb = hist_from_db_conn(pool).await.unwrap();
+        let mut nu = NuHistDb { histdb };
+        assert_eq!(nu.entries().await.unwrap(), 3);
+
+        let mut loader = TestLoader::default();
+        nu.load(&mut loader).await.unwrap();
+        let history = loader.buf;
+
+        assert_eq!(history[1].command, "cargo test");
+        assert_eq!(history[1].timestamp.timestamp_millis(), 1_673_000_005_500);
+        assert_eq!(history[1].cwd, "/home/nu/code");
+        assert_eq!(history[1].exit, 101);
+        assert_eq!(history[1].duration, 30_250_000_000);
+        assert!(history[1].hostname.starts_with("laptop:"));
+        assert_eq!(history[0].session, history[1].session);
+
+        // the missing fields fall back to atuin's defaults
+        assert_eq!(history[2].timestamp, history[1].timestamp);
+        assert_eq!(history[2].cwd, "unknown");
+        assert_eq!(history[2].exit, -1);
+        assert_eq!(history[2].duration, -1);
+        assert_ne!(history[2].session, history[1].session);
+    }
+}
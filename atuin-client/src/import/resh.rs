@@ -8,7 +8,10 @@ use serde::Deserialize;
 
 use atuin_common::utils::uuid_v4;
 
-use super::{get_histpath, unix_byte_lines, Importer, Loader};
+use super::{
+    file_watermark, get_histpath, invalid_lines, resume_file, unix_byte_lines, Importer, Loader,
+    Watermark,
+};
 use crate::history::History;
 
 #[derive(Deserialize, Debug)]
@@ -83,9 +86,12 @@ fn default_histpath() -> Result<PathBuf> {
 impl Importer for Resh {
     const NAME: &'static str = "resh";
 
-    async fn new() -> Result<Self> {
+    fn locate(path: Option<PathBuf>) -> Result<PathBuf> {
+        get_histpath(path, default_histpath)
+    }
+
+    async fn new(path: PathBuf) -> Result<Self> {
         let mut bytes = Vec::new();
-        let path = get_histpath(default_histpath)?;
         let mut f = File::open(path)?;
         f.read_to_end(&mut bytes)?;
         Ok(Self { bytes })
@@ -137,4 +143,16 @@ impl Importer for Resh {
 
         Ok(())
     }
+
+    fn watermark(&self) -> Watermark {
+        file_watermark(&self.bytes)
+    }
+
+    async fn resume(&mut self, watermark: &Watermark) -> Result<Option<usize>> {
+        resume_file(&mut self.bytes, watermark, |bytes| Self { bytes }).await
+    }
+
+    fn invalid(&self) -> usize {
+        invalid_lines(&self.bytes)
+    }
 }
@@ -8,7 +8,10 @@ use chrono::{prelude::*, Utc};
 use directories::UserDirs;
 use eyre::{eyre, Result};
 
-use super::{get_histpath, unix_byte_lines, Importer, Loader};
+use super::{
+    file_watermark, get_histpath, invalid_lines, resume_file, unix_byte_lines, Importer, Loader,
+    Watermark,
+};
 use crate::history::History;
 
 #[derive(Debug)]
@@ -41,9 +44,12 @@ fn default_histpath() -> Result<PathBuf> {
 impl Importer for Zsh {
     const NAME: &'static str = "zsh";
 
-    async fn new() -> Result<Self> {
+    fn locate(path: Option<PathBuf>) -> Result<PathBuf> {
+        get_histpath(path, default_histpath)
+    }
+
+    async fn new(path: PathBuf) -> Result<Self> {
         let mut bytes = Vec::new();
-        let path = get_histpath(default_histpath)?;
         let mut f = File::open(path)?;
         f.read_to_end(&mut bytes)?;
         Ok(Self { bytes })
@@ -94,6 +100,18 @@ impl Importer for Zsh {
 
         Ok(())
     }
+
+    fn watermark(&self) -> Watermark {
+        file_watermark(&self.bytes)
+    }
+
+    async fn resume(&mut self, watermark: &Watermark) -> Result<Option<usize>> {
+        resume_file(&mut self.bytes, watermark, |bytes| Self { bytes }).await
+    }
+
+    fn invalid(&self) -> usize {
+        invalid_lines(&self.bytes)
+    }
 }
 
 fn parse_extended(line: &str, counter: i64) -> History {
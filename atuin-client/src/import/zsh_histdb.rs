@@ -40,7 +40,7 @@ use directories::UserDirs;
 use eyre::{eyre, Result};
 use sqlx::{sqlite::SqlitePool, Pool};
 
-use super::Importer;
+use super::{explicit_histpath, resume_rows, row_watermark, Importer, Watermark};
 use crate::history::History;
 use crate::import::Loader;
 
@@ -134,11 +134,18 @@ impl Importer for ZshHistDb {
     // Not sure how this is used
     const NAME: &'static str = "zsh_histdb";
 
+    /// Uses the explicit path if given, otherwise `$HISTDB_FILE` or the default location.
+    fn locate(path: Option<PathBuf>) -> Result<PathBuf> {
+        match path {
+            Some(p) => explicit_histpath(p),
+            None => ZshHistDb::histpath(),
+        }
+    }
+
     /// Creates a new ZshHistDb and populates the history based on the pre-populated data
     /// structure.
-    async fn new() -> Result<Self> {
-        let dbpath = ZshHistDb::histpath()?;
-        let histdb_entry_vec = hist_from_db(dbpath).await?;
+    async fn new(path: PathBuf) -> Result<Self> {
+        let histdb_entry_vec = hist_from_db(path).await?;
         Ok(Self {
             histdb: histdb_entry_vec,
         })
@@ -152,6 +159,16 @@ impl Importer for ZshHistDb {
         }
         Ok(())
     }
+
+    fn watermark(&self) -> Watermark {
+        row_watermark(self.histdb.iter().map(|e| (e.id, e.argv.as_slice())))
+    }
+
+    async fn resume(&mut self, watermark: &Watermark) -> Result<Option<usize>> {
+        Ok(resume_rows(&mut self.histdb, watermark, |e| {
+            (e.id, e.argv.as_slice())
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -6,13 +6,16 @@ extern crate log;
 #[cfg(feature = "sync")]
 pub mod api_client;
 #[cfg(feature = "sync")]
-pub mod encryption;
-#[cfg(feature = "sync")]
 pub mod sync;
 
+pub mod backup;
 pub mod database;
+pub mod encryption;
 pub mod event;
+pub mod export;
 pub mod history;
 pub mod import;
+pub mod matching;
 pub mod ordering;
+pub mod secrets;
 pub mod settings;
@@ -0,0 +1,194 @@
+//! Which characters of a command matched a search query, following the same
+//! rules as the conditions built by `Database::search`, so they can be highlighted
+
+use minspan::minspan;
+
+use super::settings::SearchMode;
+
+/// The indices of the chars (not bytes) in `command` matched by `query`, in order
+pub fn match_indices(mode: SearchMode, query: &str, command: &str) -> Vec<usize> {
+    let command: Vec<char> = command.chars().collect();
+
+    let mut indices = match mode {
+        SearchMode::Prefix => pattern_indices(query, &command, true, false, false),
+        SearchMode::FullText => pattern_indices(query, &command, false, false, false),
+        SearchMode::Fuzzy => Some(fuzzy_indices(query, &command)),
+    }
+    .unwrap_or_default();
+
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+fn fuzzy_indices(query: &str, command: &[char]) -> Vec<usize> {
+    let mut indices = Vec::new();
+
+    for part in query.split(' ').filter(|p| !p.is_empty()) {
+        // inverse terms only match commands without them, so there is nothing to show
+        if part == "|" || part.starts_with('!') {
+            continue;
+        }
+
+        // smart case, as in the search
+        let case_sensitive = part.contains(char::is_uppercase);
+
+        let found = if let Some(term) = part.strip_prefix('^') {
+            pattern_indices(term, command, true, false, case_sensitive)
+        } else if let Some(term) = part.strip_suffix('$') {
+            pattern_indices(term, command, false, true, case_sensitive)
+        } else if let Some(term) = part.strip_prefix('\'') {
+            pattern_indices(term, command, false, false, case_sensitive)
+        } else {
+            subsequence_indices(part, command, case_sensitive)
+        };
+
+        // with `|`, only one side of the or has to match
+        indices.extend(found.unwrap_or_default());
+    }
+
+    indices
+}
+
+/// Match a LIKE/GLOB style pattern, where `*` and `%` match anything
+fn pattern_indices(
+    pattern: &str,
+    command: &[char],
+    anchor_start: bool,
+    anchor_end: bool,
+    case_sensitive: bool,
+) -> Option<Vec<usize>> {
+    let segments: Vec<Vec<char>> = pattern
+        .split(['*', '%'])
+        .map(|s| s.chars().collect())
+        .collect();
+    let last = segments.len() - 1;
+
+    let mut indices = Vec::new();
+    let mut pos = 0;
+    for (i, segment) in segments.iter().enumerate() {
+        if segment.is_empty() {
+            continue;
+        }
+
+        let latest = command.len().checked_sub(segment.len())?;
+        let start = if i == 0 && anchor_start {
+            0
+        } else if i == last && anchor_end {
+            latest
+        } else {
+            (pos..=latest).find(|&s| starts_with(&command[s..], segment, case_sensitive))?
+        };
+
+        if start < pos || !starts_with(&command[start..], segment, case_sensitive) {
+            return None;
+        }
+
+        indices.extend(start..start + segment.len());
+        pos = start + segment.len();
+    }
+
+    Some(indices)
+}
+
+/// Match the chars of `term` in order, anywhere in the command, preferring
+/// the shortest span of the command
+fn subsequence_indices(term: &str, command: &[char], case_sensitive: bool) -> Option<Vec<usize>> {
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_ascii_lowercase()
+        }
+    };
+
+    let term: Vec<char> = term
+        .chars()
+        .filter(|c| !matches!(c, '*' | '%'))
+        .map(fold)
+        .collect();
+    if term.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let command: Vec<char> = command.iter().copied().map(fold).collect();
+    let (from, _) = minspan::span(&term, &command)?;
+
+    let mut term = term.iter().peekable();
+    let mut indices = Vec::new();
+    for (i, c) in command.iter().enumerate().skip(from) {
+        if term.next_if_eq(&c).is_some() {
+            indices.push(i);
+        }
+    }
+
+    Some(indices)
+}
+
+fn starts_with(command: &[char], segment: &[char], case_sensitive: bool) -> bool {
+    command.len() >= segment.len()
+        && command.iter().zip(segment).all(|(a, b)| {
+            if case_sensitive {
+                a == b
+            } else {
+                a.eq_ignore_ascii_case(b)
+            }
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::match_indices;
+    use crate::settings::SearchMode;
+
+    #[test]
+    fn prefix_and_full_text() {
+        let command = "git commit -m fix";
+
+        assert_eq!(
+            match_indices(SearchMode::Prefix, "GIT c", command),
+            vec![0, 1, 2, 3, 4]
+        );
+        assert_eq!(
+            match_indices(SearchMode::Prefix, "commit", command),
+            Vec::<usize>::new()
+        );
+        assert_eq!(
+            match_indices(SearchMode::FullText, "mit", command),
+            vec![7, 8, 9]
+        );
+        assert_eq!(
+            match_indices(SearchMode::FullText, "g*fix", command),
+            vec![0, 14, 15, 16]
+        );
+    }
+
+    #[test]
+    fn fuzzy() {
+        let command = "git commit -m fix";
+
+        // the shortest span is used, so not the c and m at the start
+        assert_eq!(
+            match_indices(SearchMode::Fuzzy, "cmf", "gcm git commit -m fix"),
+            vec![8, 10, 18]
+        );
+        assert_eq!(
+            match_indices(SearchMode::Fuzzy, "^git !foo fix$", command),
+            vec![0, 1, 2, 14, 15, 16]
+        );
+        assert_eq!(
+            match_indices(SearchMode::Fuzzy, "'com | nope", command),
+            vec![4, 5, 6]
+        );
+
+        // smart case
+        assert_eq!(
+            match_indices(SearchMode::Fuzzy, "Git", command),
+            Vec::<usize>::new()
+        );
+        assert_eq!(
+            match_indices(SearchMode::Fuzzy, "Git", "Gitlab"),
+            vec![0, 1, 2]
+        );
+    }
+}
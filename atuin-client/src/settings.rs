@@ -1,13 +1,20 @@
 use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fmt,
     io::prelude::*,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use chrono::{prelude::*, Utc};
 use clap::ValueEnum;
-use config::{Config, Environment, File as ConfigFile, FileFormat};
+use config::{
+    builder::DefaultState, Config, ConfigBuilder, Environment, File as ConfigFile, FileFormat,
+};
 use eyre::{eyre, Context, Result};
 use fs_err::{create_dir_all, File};
+use itertools::Itertools;
 use parse_duration::parse;
 use regex::RegexSet;
 use semver::Version;
@@ -17,8 +24,10 @@ pub const HISTORY_PAGE_SIZE: i64 = 100;
 pub const LAST_SYNC_FILENAME: &str = "last_sync_time";
 pub const LAST_VERSION_CHECK_FILENAME: &str = "last_version_check_time";
 pub const LATEST_VERSION_FILENAME: &str = "latest_version";
+pub const LAST_SEARCH_MODE_FILENAME: &str = "last_search_mode";
+pub const LAST_EXPIRE_FILENAME: &str = "last_expire_time";
 
-#[derive(Clone, Debug, Deserialize, Copy, ValueEnum)]
+#[derive(Clone, Debug, Deserialize, Copy, PartialEq, Eq, ValueEnum)]
 pub enum SearchMode {
     #[serde(rename = "prefix")]
     Prefix,
@@ -31,6 +40,23 @@ pub enum SearchMode {
     Fuzzy,
 }
 
+impl SearchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchMode::Prefix => "PREFIX",
+            SearchMode::FullText => "FULLTEXT",
+            SearchMode::Fuzzy => "FUZZY",
+        }
+    }
+
+    /// The mode after this one, wrapping around to the first
+    pub fn next(self) -> Self {
+        let modes = Self::value_variants();
+        let i = modes.iter().position(|m| *m == self).unwrap_or(0);
+        modes[(i + 1) % modes.len()]
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Copy, PartialEq, Eq, ValueEnum)]
 pub enum FilterMode {
     #[serde(rename = "global")]
@@ -44,6 +70,12 @@ pub enum FilterMode {
 
     #[serde(rename = "directory")]
     Directory = 3,
+
+    #[serde(rename = "subtree")]
+    Subtree = 4,
+
+    #[serde(rename = "workspace")]
+    Workspace = 5,
 }
 
 impl FilterMode {
@@ -53,6 +85,8 @@ impl FilterMode {
             FilterMode::Host => "HOST",
             FilterMode::Session => "SESSION",
             FilterMode::Directory => "DIRECTORY",
+            FilterMode::Subtree => "SUBTREE",
+            FilterMode::Workspace => "WORKSPACE",
         }
     }
 }
@@ -107,6 +141,281 @@ pub enum WordJumpMode {
     Subl,
 }
 
+/// What happens to a command accepted in the interactive search
+#[derive(Clone, Debug, Deserialize, Copy, PartialEq, Eq)]
+pub enum AcceptMode {
+    /// Put it on the command line, to be edited before running
+    #[serde(rename = "edit")]
+    Edit,
+
+    /// Run it straight away
+    #[serde(rename = "run")]
+    Run,
+}
+
+/// How several marked commands are joined when accepted together
+#[derive(Clone, Debug, Deserialize, Copy)]
+pub enum MultiSelectJoin {
+    /// One command per line
+    #[serde(rename = "newline")]
+    Newline,
+
+    /// `a && b`, so the rest are skipped if one fails
+    #[serde(rename = "and")]
+    And,
+}
+
+impl MultiSelectJoin {
+    pub fn separator(self) -> &'static str {
+        match self {
+            MultiSelectJoin::Newline => "\n",
+            MultiSelectJoin::And => " && ",
+        }
+    }
+}
+
+/// What happens to a command that looks like it contains a secret, such as an API token
+#[derive(Clone, Debug, Deserialize, Copy, PartialEq, Eq)]
+pub enum SecretPolicy {
+    /// Save it as it is
+    #[serde(rename = "off")]
+    Off,
+
+    /// Leave it out of the history
+    #[serde(rename = "drop")]
+    Drop,
+
+    /// Save it with the secret replaced
+    #[serde(rename = "redact")]
+    Redact,
+
+    /// Save it as it is, but never sync it
+    #[serde(rename = "local-only")]
+    LocalOnly,
+}
+
+#[derive(Clone, Debug, Deserialize, Copy, PartialEq, Eq)]
+pub enum KeymapMode {
+    #[serde(rename = "emacs")]
+    Emacs,
+
+    #[serde(rename = "vim")]
+    Vim,
+}
+
+/// How part of the interactive search is drawn. Colors are names such as
+/// `red` or `darkgray`, `#rrggbb`, or a 256 color index
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ThemeStyle {
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reversed: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Copy, PartialEq, Eq, Default)]
+pub enum ThemeName {
+    #[default]
+    #[serde(rename = "default")]
+    Default,
+
+    /// For terminals with a light background
+    #[serde(rename = "light")]
+    Light,
+
+    /// No colors at all, as with `NO_COLOR`
+    #[serde(rename = "mono")]
+    Mono,
+}
+
+/// A built-in theme, with any of its styles replaced
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub name: ThemeName,
+    /// The characters of each result that matched the query
+    pub highlight: Option<ThemeStyle>,
+    /// The command of the selected result
+    pub selected: Option<ThemeStyle>,
+    /// The duration of commands that succeeded
+    pub success: Option<ThemeStyle>,
+    /// The duration of commands that failed
+    pub failure: Option<ThemeStyle>,
+    /// How long ago each command was ran
+    pub time: Option<ThemeStyle>,
+    /// The title at the top left
+    pub title: Option<ThemeStyle>,
+    /// The update available banner
+    pub alert: Option<ThemeStyle>,
+    /// Key names in the help, and field names in the details
+    pub label: Option<ThemeStyle>,
+    /// The help, stats and other less important text
+    pub muted: Option<ThemeStyle>,
+}
+
+/// How long history is kept, and how much of it. Pinned history is always kept
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Retention {
+    /// Written as eg "90d", parsed the same way as `sync_frequency`
+    #[serde(deserialize_with = "deserialize_age")]
+    pub max_age: Option<chrono::Duration>,
+    /// How many of the newest entries to keep
+    pub max_entries: Option<i64>,
+}
+
+impl Retention {
+    pub fn is_set(&self) -> bool {
+        self.max_age.is_some() || self.max_entries.is_some()
+    }
+
+    /// History that started before this has expired
+    pub fn cutoff(&self) -> Option<chrono::DateTime<Utc>> {
+        Utc::now().checked_sub_signed(self.max_age?)
+    }
+}
+
+// an invalid age is reported when the settings load, rather than each time history expires
+fn deserialize_age<'de, D>(deserializer: D) -> Result<Option<chrono::Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let age = String::deserialize(deserializer)?;
+    parse(&age)
+        .map_err(|e| e.to_string())
+        .and_then(|d| chrono::Duration::from_std(d).map_err(|e| e.to_string()))
+        .map(Some)
+        .map_err(|e| {
+            serde::de::Error::custom(format!("invalid history_retention.max_age {age:?}: {e}"))
+        })
+}
+
+/// Something that can be bound to a key in the interactive search
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum KeyAction {
+    /// Accept the selected entry, doing what `accept_mode` asks for
+    Accept,
+    /// Accept the selected entry, and run it straight away
+    AcceptRun,
+    /// Accept the selected entry, to be edited before running
+    AcceptEdit,
+    /// Accept the entry this many places above the selected one
+    Select(u8),
+    /// Exit, returning what `exit_mode` asks for
+    Exit,
+    ReturnOriginal,
+    ReturnQuery,
+    MoveUp,
+    MoveDown,
+    /// Move down, or exit if already at the bottom of the list
+    MoveDownOrExit,
+    PageUp,
+    PageDown,
+    CursorLeft,
+    CursorRight,
+    CursorWordLeft,
+    CursorWordRight,
+    CursorStart,
+    CursorEnd,
+    DeleteCharBefore,
+    DeleteCharAfter,
+    DeleteWordBefore,
+    DeleteWordAfter,
+    /// Delete back to the previous whitespace
+    DeleteToWhitespace,
+    ClearInput,
+    CycleFilterMode,
+    CycleSearchMode,
+    ToggleDetails,
+    /// Delete the selected entry from history
+    DeleteEntry,
+    /// Copy the selected command to the clipboard
+    Copy,
+    /// Mark or unmark the selected entry, to accept several at once
+    ToggleMark,
+}
+
+impl KeyAction {
+    const NAMED: &'static [(&'static str, KeyAction)] = &[
+        ("accept", KeyAction::Accept),
+        ("accept-run", KeyAction::AcceptRun),
+        ("accept-edit", KeyAction::AcceptEdit),
+        ("exit", KeyAction::Exit),
+        ("return-original", KeyAction::ReturnOriginal),
+        ("return-query", KeyAction::ReturnQuery),
+        ("move-up", KeyAction::MoveUp),
+        ("move-down", KeyAction::MoveDown),
+        ("move-down-or-exit", KeyAction::MoveDownOrExit),
+        ("page-up", KeyAction::PageUp),
+        ("page-down", KeyAction::PageDown),
+        ("cursor-left", KeyAction::CursorLeft),
+        ("cursor-right", KeyAction::CursorRight),
+        ("cursor-word-left", KeyAction::CursorWordLeft),
+        ("cursor-word-right", KeyAction::CursorWordRight),
+        ("cursor-start", KeyAction::CursorStart),
+        ("cursor-end", KeyAction::CursorEnd),
+        ("delete-char-before", KeyAction::DeleteCharBefore),
+        ("delete-char-after", KeyAction::DeleteCharAfter),
+        ("delete-word-before", KeyAction::DeleteWordBefore),
+        ("delete-word-after", KeyAction::DeleteWordAfter),
+        ("delete-to-whitespace", KeyAction::DeleteToWhitespace),
+        ("clear-input", KeyAction::ClearInput),
+        ("cycle-filter-mode", KeyAction::CycleFilterMode),
+        ("cycle-search-mode", KeyAction::CycleSearchMode),
+        ("toggle-details", KeyAction::ToggleDetails),
+        ("delete-entry", KeyAction::DeleteEntry),
+        ("copy", KeyAction::Copy),
+        ("toggle-mark", KeyAction::ToggleMark),
+    ];
+}
+
+impl FromStr for KeyAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, action)) = Self::NAMED.iter().find(|(name, _)| *name == s) {
+            return Ok(*action);
+        }
+
+        match s.strip_prefix("select-").map(str::parse) {
+            Some(Ok(n @ 1..=9)) => Ok(KeyAction::Select(n)),
+            _ => {
+                let valid = Self::NAMED.iter().map(|(name, _)| *name).join(", ");
+                Err(format!(
+                    "unknown key action {s:?}, expected one of: {valid}, select-1 to select-9"
+                ))
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for KeyAction {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for KeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyAction::Select(n) => write!(f, "select-{n}"),
+            action => {
+                let (name, _) = Self::NAMED
+                    .iter()
+                    .find(|(_, a)| a == action)
+                    .expect("every action is named");
+                f.write_str(name)
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct Settings {
     pub dialect: Dialect,
@@ -124,11 +433,24 @@ pub struct Settings {
     pub shell_up_key_binding: bool,
     pub show_preview: bool,
     pub exit_mode: ExitMode,
+    pub accept_mode: AcceptMode,
+    pub remember_search_mode: bool,
     pub word_jump_mode: WordJumpMode,
+    pub keymap_mode: KeymapMode,
+    pub inline_height: u16,
+    pub multi_select_join: MultiSelectJoin,
     pub word_chars: String,
     pub scroll_context_lines: usize,
     #[serde(with = "serde_regex", default = "RegexSet::empty")]
     pub history_filter: RegexSet,
+    pub secret_policy: SecretPolicy,
+    #[serde(default)]
+    pub history_retention: Retention,
+    /// Extra key bindings for the interactive search, on top of the defaults
+    #[serde(default)]
+    pub keys: HashMap<String, KeyAction>,
+    #[serde(default)]
+    pub theme: Theme,
 
     // This is automatically loaded when settings is created. Do not set in
     // config! Keep secrets and settings apart.
@@ -193,10 +515,37 @@ impl Settings {
         Settings::load_time_from_file(LAST_SYNC_FILENAME)
     }
 
+    pub fn save_expire_time() -> Result<()> {
+        Settings::save_current_time(LAST_EXPIRE_FILENAME)
+    }
+
+    pub fn last_expire() -> Result<chrono::DateTime<Utc>> {
+        Settings::load_time_from_file(LAST_EXPIRE_FILENAME)
+    }
+
     pub fn last_version_check() -> Result<chrono::DateTime<Utc>> {
         Settings::load_time_from_file(LAST_VERSION_CHECK_FILENAME)
     }
 
+    pub fn save_search_mode(mode: SearchMode) -> Result<()> {
+        let name = mode
+            .to_possible_value()
+            .expect("search modes are never skipped");
+        Settings::save_to_data_dir(LAST_SEARCH_MODE_FILENAME, name.get_name())
+    }
+
+    /// The search mode last switched to in the interactive search
+    pub fn last_search_mode() -> Option<SearchMode> {
+        let name = Settings::read_from_data_dir(LAST_SEARCH_MODE_FILENAME)?;
+        SearchMode::from_str(name.trim(), true).ok()
+    }
+
+    /// Whether a command is kept out of the history, because it starts with a
+    /// space or matches `history_filter`
+    pub fn is_filtered(&self, command: &str) -> bool {
+        command.starts_with(' ') || self.history_filter.is_match(command)
+    }
+
     pub fn should_sync(&self) -> Result<bool> {
         if !self.auto_sync || !PathBuf::from(self.session_path.as_str()).exists() {
             return Ok(false);
@@ -211,6 +560,16 @@ impl Settings {
         }
     }
 
+    /// Whether it is time to remove the history that `history_retention` no longer keeps.
+    /// This is done at most once an hour
+    pub fn should_expire(&self) -> Result<bool> {
+        if !self.history_retention.is_set() {
+            return Ok(false);
+        }
+
+        Ok(Utc::now() - Settings::last_expire()? >= chrono::Duration::hours(1))
+    }
+
     fn needs_update_check(&self) -> Result<bool> {
         let last_check = Settings::last_version_check()?;
         let diff = Utc::now() - last_check;
@@ -291,11 +650,45 @@ impl Settings {
 
         config_file.push("config.toml");
 
+        let mut config_builder = Self::builder(&data_dir)?.add_source(
+            Environment::with_prefix("atuin")
+                .prefix_separator("_")
+                .separator("__"),
+        );
+
+        config_builder = if config_file.exists() {
+            config_builder.add_source(ConfigFile::new(
+                config_file.to_str().unwrap(),
+                FileFormat::Toml,
+            ))
+        } else {
+            let example_config = include_bytes!("../config.toml");
+            let mut file = File::create(config_file).wrap_err("could not create config file")?;
+            file.write_all(example_config)
+                .wrap_err("could not write default config file")?;
+
+            config_builder
+        };
+
+        Self::build(config_builder)
+    }
+
+    /// The settings in `config`, a TOML string, with the data kept in `data_dir`.
+    /// Neither the environment nor the config file are read, so this is what
+    /// tests use
+    pub fn from_toml(data_dir: &Path, config: &str) -> Result<Self> {
+        let config_builder =
+            Self::builder(data_dir)?.add_source(ConfigFile::from_str(config, FileFormat::Toml));
+
+        Self::build(config_builder)
+    }
+
+    fn builder(data_dir: &Path) -> Result<ConfigBuilder<DefaultState>> {
         let db_path = data_dir.join("history.db");
         let key_path = data_dir.join("key");
         let session_path = data_dir.join("session");
 
-        let mut config_builder = Config::builder()
+        Ok(Config::builder()
             .set_default("db_path", db_path.to_str())?
             .set_default("key_path", key_path.to_str())?
             .set_default("session_path", session_path.to_str())?
@@ -309,34 +702,23 @@ impl Settings {
             .set_default("shell_up_key_binding", false)?
             .set_default("show_preview", false)?
             .set_default("exit_mode", "return-original")?
+            .set_default("accept_mode", "edit")?
+            .set_default("remember_search_mode", false)?
             .set_default("session_token", "")?
             .set_default("style", "auto")?
             .set_default("word_jump_mode", "emacs")?
+            .set_default("keymap_mode", "emacs")?
+            .set_default("inline_height", 0)?
+            .set_default("multi_select_join", "newline")?
+            .set_default("secret_policy", "redact")?
             .set_default(
                 "word_chars",
                 "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789",
             )?
-            .set_default("scroll_context_lines", 1)?
-            .add_source(
-                Environment::with_prefix("atuin")
-                    .prefix_separator("_")
-                    .separator("__"),
-            );
-
-        config_builder = if config_file.exists() {
-            config_builder.add_source(ConfigFile::new(
-                config_file.to_str().unwrap(),
-                FileFormat::Toml,
-            ))
-        } else {
-            let example_config = include_bytes!("../config.toml");
-            let mut file = File::create(config_file).wrap_err("could not create config file")?;
-            file.write_all(example_config)
-                .wrap_err("could not write default config file")?;
-
-            config_builder
-        };
+            .set_default("scroll_context_lines", 1)?)
+    }
 
+    fn build(config_builder: ConfigBuilder<DefaultState>) -> Result<Self> {
         let config = config_builder.build()?;
         let mut settings: Settings = config
             .try_deserialize()
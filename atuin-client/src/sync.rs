@@ -28,10 +28,60 @@ pub fn hash_str(string: &str) -> String {
 // with year, then find the week, then the day, then the hour, then download it
 // all! The current naive approach will do for now.
 
+// Deleting history changes the count on one side only, so deletes are synced
+// before anything else: ours are sent to the server, and those from other
+// machines are applied here, leaving both counts comparable again.
+async fn sync_deletes(
+    last_sync: DateTime<Utc>,
+    client: &api_client::Client<'_>,
+    db: &mut impl Database,
+) -> Result<()> {
+    debug!("starting sync deletes");
+
+    let deletes = db.unsynced_deletes().await?;
+
+    if !deletes.is_empty() {
+        if !client.delete_history(&deletes).await? {
+            debug!("server does not support deleting history");
+            return Ok(());
+        }
+
+        db.save_synced_deletes(&deletes).await?;
+    }
+
+    // the same hour of leeway as downloading history
+    let since = last_sync - chrono::Duration::hours(1);
+    let deleted = match client.deleted_history(since).await? {
+        Some(deleted) => deleted,
+        None => {
+            debug!("server does not support deleting history");
+            return Ok(());
+        }
+    };
+
+    let mut local = Vec::new();
+
+    for id in &deleted {
+        match db.load(id).await {
+            Ok(h) => local.push(h),
+            Err(sqlx::Error::RowNotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    debug!("deleting {} history entries deleted elsewhere", local.len());
+
+    db.delete_bulk(&local).await?;
+    db.save_synced_deletes(&deleted).await?;
+
+    Ok(())
+}
+
 // Check if remote has things we don't, and if so, download them.
 // Returns (num downloaded, total local)
 async fn sync_download(
     force: bool,
+    mut last_sync: DateTime<Utc>,
     client: &api_client::Client<'_>,
     db: &mut (impl Database + Send),
 ) -> Result<(i64, i64)> {
@@ -39,15 +89,9 @@ async fn sync_download(
 
     let remote_count = client.count().await?;
 
-    let initial_local = db.history_count().await?;
+    let initial_local = db.sync_count().await?;
     let mut local_count = initial_local;
 
-    let mut last_sync = if force {
-        Utc.timestamp_millis(0)
-    } else {
-        Settings::last_sync()?
-    };
-
     let mut last_timestamp = Utc.timestamp_millis(0);
 
     let host = if force { Some(String::from("")) } else { None };
@@ -59,7 +103,7 @@ async fn sync_download(
 
         db.save_bulk(&page).await?;
 
-        local_count = db.history_count().await?;
+        local_count = db.sync_count().await?;
 
         if page.len() < HISTORY_PAGE_SIZE.try_into().unwrap() {
             break;
@@ -96,7 +140,7 @@ async fn sync_upload(
     let initial_remote_count = client.count().await?;
     let mut remote_count = initial_remote_count;
 
-    let local_count = db.history_count().await?;
+    let local_count = db.sync_count().await?;
 
     debug!("remote has {}, we have {}", remote_count, local_count);
 
@@ -140,6 +184,25 @@ async fn sync_upload(
 }
 
 pub async fn sync(settings: &Settings, force: bool, db: &mut (impl Database + Send)) -> Result<()> {
+    let last_sync = if force {
+        Utc.timestamp_millis(0)
+    } else {
+        Settings::last_sync()?
+    };
+
+    sync_since(settings, force, last_sync, db).await?;
+
+    Settings::save_sync_time()?;
+
+    Ok(())
+}
+
+async fn sync_since(
+    settings: &Settings,
+    force: bool,
+    last_sync: DateTime<Utc>,
+    db: &mut impl Database,
+) -> Result<()> {
     db.merge_events().await?;
 
     let client = api_client::Client::new(
@@ -148,13 +211,287 @@ pub async fn sync(settings: &Settings, force: bool, db: &mut (impl Database + Se
         load_encoded_key(settings)?,
     )?;
 
+    sync_deletes(last_sync, &client, db).await?;
+
     sync_upload(settings, force, &client, db).await?;
 
-    let download = sync_download(force, &client, db).await?;
+    let download = sync_download(force, last_sync, &client, db).await?;
 
     debug!("sync downloaded {}", download.0);
 
-    Settings::save_sync_time()?;
-
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        net::TcpListener,
+        sync::{Arc, Mutex},
+    };
+
+    use async_trait::async_trait;
+    use atuin_server::{
+        calendar::{TimePeriod, TimePeriodInfo},
+        database::Database as ServerDatabase,
+        models::{History as ServerHistory, NewHistory, NewSession, NewUser, Session, User},
+        router,
+        settings::Settings as ServerSettings,
+    };
+
+    use super::*;
+    use crate::{database::Sqlite, history::History};
+
+    // just enough of the server's database to sync against. Anything sync does
+    // not use is not found
+    #[derive(Clone, Default)]
+    struct Server {
+        history: Arc<Mutex<Vec<ServerHistory>>>,
+        deleted: Arc<Mutex<Vec<(String, chrono::NaiveDateTime)>>>,
+    }
+
+    impl Server {
+        fn ids(&self) -> Vec<String> {
+            let history = self.history.lock().unwrap();
+            history.iter().map(|h| h.client_id.clone()).collect()
+        }
+    }
+
+    #[async_trait]
+    impl ServerDatabase for Server {
+        async fn get_session(&self, _: &str) -> sqlx::Result<Session> {
+            Err(sqlx::Error::RowNotFound)
+        }
+
+        async fn get_session_user(&self, _: &str) -> sqlx::Result<User> {
+            Ok(User {
+                id: 1,
+                username: "ellie".to_string(),
+                email: "ellie@atuin.sh".to_string(),
+                password: String::new(),
+            })
+        }
+
+        async fn add_session(&self, _: &NewSession) -> sqlx::Result<()> {
+            Err(sqlx::Error::RowNotFound)
+        }
+
+        async fn get_user(&self, _: &str) -> sqlx::Result<User> {
+            Err(sqlx::Error::RowNotFound)
+        }
+
+        async fn get_user_session(&self, _: &User) -> sqlx::Result<Session> {
+            Err(sqlx::Error::RowNotFound)
+        }
+
+        async fn add_user(&self, _: &NewUser) -> sqlx::Result<i64> {
+            Err(sqlx::Error::RowNotFound)
+        }
+
+        async fn count_history(&self, _: &User) -> sqlx::Result<i64> {
+            Ok(self.history.lock().unwrap().len() as i64)
+        }
+
+        async fn count_history_cached(&self, _: &User) -> sqlx::Result<i64> {
+            Err(sqlx::Error::RowNotFound)
+        }
+
+        async fn count_history_range(
+            &self,
+            _: &User,
+            _: chrono::NaiveDateTime,
+            _: chrono::NaiveDateTime,
+        ) -> sqlx::Result<i64> {
+            Err(sqlx::Error::RowNotFound)
+        }
+
+        async fn count_history_day(&self, _: &User, _: chrono::NaiveDate) -> sqlx::Result<i64> {
+            Err(sqlx::Error::RowNotFound)
+        }
+
+        async fn count_history_month(&self, _: &User, _: chrono::NaiveDate) -> sqlx::Result<i64> {
+            Err(sqlx::Error::RowNotFound)
+        }
+
+        async fn count_history_year(&self, _: &User, _: i32) -> sqlx::Result<i64> {
+            Err(sqlx::Error::RowNotFound)
+        }
+
+        async fn list_history(
+            &self,
+            _: &User,
+            created_after: chrono::NaiveDateTime,
+            since: chrono::NaiveDateTime,
+            host: &str,
+        ) -> sqlx::Result<Vec<ServerHistory>> {
+            let history = self.history.lock().unwrap();
+            let mut page: Vec<ServerHistory> = history
+                .iter()
+                .filter(|h| {
+                    h.hostname != host && h.created_at >= created_after && h.timestamp >= since
+                })
+                .map(|h| ServerHistory {
+                    id: h.id,
+                    client_id: h.client_id.clone(),
+                    user_id: h.user_id,
+                    hostname: h.hostname.clone(),
+                    timestamp: h.timestamp,
+                    data: h.data.clone(),
+                    created_at: h.created_at,
+                })
+                .collect();
+            page.sort_by_key(|h| h.timestamp);
+            page.truncate(HISTORY_PAGE_SIZE as usize);
+
+            Ok(page)
+        }
+
+        async fn add_history(&self, new: &[NewHistory]) -> sqlx::Result<()> {
+            let deleted = self.deleted.lock().unwrap();
+            let mut history = self.history.lock().unwrap();
+
+            for h in new {
+                if history.iter().any(|e| e.client_id == h.client_id)
+                    || deleted.iter().any(|(id, _)| *id == h.client_id)
+                {
+                    continue;
+                }
+
+                let id = history.len() as i64;
+                history.push(ServerHistory {
+                    id,
+                    client_id: h.client_id.clone(),
+                    user_id: h.user_id,
+                    hostname: h.hostname.clone(),
+                    timestamp: h.timestamp,
+                    data: h.data.clone(),
+                    created_at: Utc::now().naive_utc(),
+                });
+            }
+
+            Ok(())
+        }
+
+        async fn delete_history(&self, _: &User, ids: &[String]) -> sqlx::Result<()> {
+            let mut deleted = self.deleted.lock().unwrap();
+            deleted.extend(ids.iter().map(|id| (id.clone(), Utc::now().naive_utc())));
+            self.history
+                .lock()
+                .unwrap()
+                .retain(|h| !ids.contains(&h.client_id));
+
+            Ok(())
+        }
+
+        async fn deleted_history(
+            &self,
+            _: &User,
+            since: chrono::NaiveDateTime,
+        ) -> sqlx::Result<Vec<String>> {
+            let deleted = self.deleted.lock().unwrap();
+            Ok(deleted
+                .iter()
+                .filter(|(_, at)| *at >= since)
+                .map(|(id, _)| id.clone())
+                .collect())
+        }
+
+        async fn oldest_history(&self, _: &User) -> sqlx::Result<ServerHistory> {
+            Err(sqlx::Error::RowNotFound)
+        }
+
+        async fn calendar(
+            &self,
+            _: &User,
+            _: TimePeriod,
+            _: u64,
+            _: u64,
+        ) -> sqlx::Result<HashMap<u64, TimePeriodInfo>> {
+            Err(sqlx::Error::RowNotFound)
+        }
+    }
+
+    fn serve(server: Server) -> String {
+        let settings = ServerSettings {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            path: String::new(),
+            db_uri: String::new(),
+            open_registration: false,
+            max_history_length: 0,
+            register_webhook_url: None,
+            register_webhook_username: String::new(),
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let app = router::router(server, settings);
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        address
+    }
+
+    fn history(command: &str) -> History {
+        History::new(
+            Utc::now(),
+            command.to_string(),
+            "/home/ellie".to_string(),
+            0,
+            1,
+            None,
+            None,
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sync_after_delete() {
+        // only the key is kept here
+        let dir = std::env::temp_dir().join(format!("atuin-sync-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let server = Server::default();
+        let mut settings = Settings::from_toml(&dir, "").unwrap();
+        settings.sync_address = serve(server.clone());
+        settings.session_token = "token".to_string();
+
+        let never = Utc.timestamp_millis(0);
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        let (ls, cd) = (history("ls"), history("cd /"));
+        db.save(&ls).await.unwrap();
+        db.save(&cd).await.unwrap();
+        let last_sync = Utc::now();
+        sync_since(&settings, false, never, &mut db).await.unwrap();
+        assert_eq!(server.ids().len(), 2);
+
+        // the delete reaches the server, and does not stop what comes after from uploading
+        db.delete(&ls).await.unwrap();
+        let pwd = history("pwd");
+        db.save(&pwd).await.unwrap();
+        sync_since(&settings, false, last_sync, &mut db)
+            .await
+            .unwrap();
+
+        let mut ids = server.ids();
+        ids.sort();
+        let mut expected = vec![cd.id.clone(), pwd.id.clone()];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        // and is applied on another machine that has it
+        let mut other = Sqlite::new("sqlite::memory:").await.unwrap();
+        other.save(&ls).await.unwrap();
+        other.save(&cd).await.unwrap();
+        other.save(&pwd).await.unwrap();
+        sync_since(&settings, true, never, &mut other)
+            .await
+            .unwrap();
+        assert_eq!(other.history_count().await.unwrap(), 2);
+        assert!(other.unsynced_deletes().await.unwrap().is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -55,6 +55,22 @@ pub struct SyncHistoryResponse {
     pub history: Vec<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteHistoryRequest {
+    // the client ids of the history to delete
+    pub ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncDeletedRequest {
+    pub since: chrono::DateTime<chrono::FixedOffset>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncDeletedResponse {
+    pub ids: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse<'a> {
     pub reason: Cow<'a, str>,
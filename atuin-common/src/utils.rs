@@ -1,5 +1,5 @@
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use chrono::NaiveDate;
 use uuid::Uuid;
@@ -48,6 +48,14 @@ pub fn get_current_dir() -> String {
     }
 }
 
+/// Walk up from `path` looking for the root of the enclosing git repository
+pub fn git_root(path: impl AsRef<Path>) -> Option<PathBuf> {
+    path.as_ref()
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
 pub fn get_days_from_month(year: i32, month: u32) -> i64 {
     NaiveDate::from_ymd(
         match month {
@@ -102,6 +110,21 @@ mod tests {
         env::remove_var("XDG_DATA_HOME");
     }
 
+    #[test]
+    fn test_git_root() {
+        let root = env::temp_dir().join(format!("atuin-git-root-{}", uuid_v4()));
+        let nested = root.join("crates").join("client");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(git_root(&nested), None);
+
+        std::fs::create_dir(root.join(".git")).unwrap();
+        assert_eq!(git_root(&nested), Some(root.clone()));
+        assert_eq!(git_root(&root), Some(root.clone()));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
     fn test_data_dir() {
         env::set_var("HOME", "/home/user");
         env::remove_var("XDG_DATA_HOME");
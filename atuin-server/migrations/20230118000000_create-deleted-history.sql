@@ -0,0 +1,13 @@
+-- History that has been deleted by a client. The history itself is removed,
+-- and this stops it from being uploaded again by clients that still have it.
+-- Clients fetch it to delete their own copies.
+create table deleted_history (
+	id bigserial primary key,
+	client_id text not null,
+	user_id bigserial not null,
+	deleted_at timestamp not null default current_timestamp,
+
+	unique(user_id, client_id)
+);
+
+create index deleted_history_user_deleted_at on deleted_history(user_id, deleted_at);
@@ -48,6 +48,15 @@ pub trait Database {
 
     async fn add_history(&self, history: &[NewHistory]) -> Result<()>;
 
+    /// Delete history by its client id, and keep it from being added again
+    async fn delete_history(&self, user: &User, ids: &[String]) -> Result<()>;
+    /// The client ids of the history deleted since `since`
+    async fn deleted_history(
+        &self,
+        user: &User,
+        since: chrono::NaiveDateTime,
+    ) -> Result<Vec<String>>;
+
     async fn oldest_history(&self, user: &User) -> Result<History>;
 
     async fn calendar(
@@ -269,10 +278,14 @@ impl Database for Postgres {
                 continue;
             }
 
+            // history that has been deleted is not added back by clients that still have it
             sqlx::query(
                 "insert into history
                     (client_id, user_id, hostname, timestamp, data) 
-                values ($1, $2, $3, $4, $5)
+                select $1, $2, $3, $4, $5
+                where not exists (
+                    select 1 from deleted_history where user_id = $2 and client_id = $1
+                )
                 on conflict do nothing
                 ",
             )
@@ -290,6 +303,51 @@ impl Database for Postgres {
         Ok(())
     }
 
+    #[instrument(skip_all)]
+    async fn delete_history(&self, user: &User, ids: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for id in ids {
+            sqlx::query(
+                "insert into deleted_history (client_id, user_id)
+                values ($1, $2)
+                on conflict do nothing",
+            )
+            .bind(id)
+            .bind(user.id)
+            .execute(&mut tx)
+            .await?;
+
+            // the count trigger takes it off the cached count
+            sqlx::query("delete from history where user_id = $1 and client_id = $2")
+                .bind(user.id)
+                .bind(id)
+                .execute(&mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn deleted_history(
+        &self,
+        user: &User,
+        since: chrono::NaiveDateTime,
+    ) -> Result<Vec<String>> {
+        sqlx::query_scalar(
+            "select client_id from deleted_history
+            where user_id = $1
+            and deleted_at >= $2",
+        )
+        .bind(user.id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+    }
+
     #[instrument(skip_all)]
     async fn add_user(&self, user: &NewUser) -> Result<i64> {
         let email: &str = &user.email;
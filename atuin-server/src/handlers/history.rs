@@ -104,6 +104,42 @@ pub async fn add<DB: Database>(
     Ok(())
 }
 
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn delete<DB: Database>(
+    user: User,
+    state: State<AppState<DB>>,
+    Json(req): Json<DeleteHistoryRequest>,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    debug!("request to delete {} history items", req.ids.len());
+
+    let db = &state.0.database;
+    if let Err(e) = db.delete_history(&user, &req.ids).await {
+        error!("failed to delete history: {}", e);
+
+        return Err(ErrorResponse::reply("failed to delete history")
+            .with_status(StatusCode::INTERNAL_SERVER_ERROR));
+    };
+
+    Ok(())
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn deleted<DB: Database>(
+    req: Query<SyncDeletedRequest>,
+    user: User,
+    state: State<AppState<DB>>,
+) -> Result<Json<SyncDeletedResponse>, ErrorResponseStatus<'static>> {
+    let db = &state.0.database;
+    match db.deleted_history(&user, req.since.naive_utc()).await {
+        Ok(ids) => Ok(Json(SyncDeletedResponse { ids })),
+        Err(e) => {
+            error!("failed to load deleted history: {}", e);
+            Err(ErrorResponse::reply("failed to load deleted history")
+                .with_status(StatusCode::INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
 #[instrument(skip_all, fields(user.id = user.id))]
 pub async fn calendar<DB: Database>(
     Path(focus): Path<String>,
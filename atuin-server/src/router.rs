@@ -68,7 +68,11 @@ pub fn router<DB: Database + Clone + Send + Sync + 'static>(
         .route("/sync/count", get(handlers::history::count))
         .route("/sync/history", get(handlers::history::list))
         .route("/sync/calendar/:focus", get(handlers::history::calendar))
-        .route("/history", post(handlers::history::add))
+        .route("/sync/deleted", get(handlers::history::deleted))
+        .route(
+            "/history",
+            post(handlers::history::add).delete(handlers::history::delete),
+        )
         .route("/user/:username", get(handlers::user::get))
         .route("/register", post(handlers::user::register))
         .route("/login", post(handlers::user::login));
@@ -9,6 +9,7 @@ use env_logger::Builder;
 #[cfg(feature = "sync")]
 mod sync;
 
+mod backup;
 mod history;
 mod import;
 mod search;
@@ -22,14 +23,17 @@ pub enum Cmd {
     History(history::Cmd),
 
     /// Import shell history from file
-    #[command(subcommand)]
     Import(import::Cmd),
 
     /// Calculate statistics for your history
     Stats(stats::Cmd),
 
+    /// Back up and restore local history, encrypted with your key
+    #[command(subcommand)]
+    Backup(backup::Cmd),
+
     /// Interactive history search
-    Search(search::Cmd),
+    Search(Box<search::Cmd>),
 
     #[cfg(feature = "sync")]
     #[command(flatten)]
@@ -51,8 +55,9 @@ impl Cmd {
 
         match self {
             Self::History(history) => history.run(&settings, &mut db).await,
-            Self::Import(import) => import.run(&mut db).await,
+            Self::Import(import) => import.run(&settings, &mut db).await,
             Self::Stats(stats) => stats.run(&mut db, &settings).await,
+            Self::Backup(backup) => backup.run(&settings, &mut db).await,
             Self::Search(search) => search.run(&mut db, &mut settings).await,
             #[cfg(feature = "sync")]
             Self::Sync(sync) => sync.run(settings, &mut db).await,
@@ -0,0 +1,89 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use eyre::{bail, Result, WrapErr};
+use fs_err as fs;
+
+use atuin_client::{
+    backup::Backup,
+    database::Database,
+    encryption::{load_key, Key},
+    settings::Settings,
+};
+
+#[derive(Subcommand)]
+#[command(infer_subcommands = true)]
+pub enum Cmd {
+    /// Write all local history to an encrypted backup file
+    Create { file: PathBuf },
+
+    /// Add the history in a backup file to the local history
+    Restore { file: PathBuf },
+}
+
+impl Cmd {
+    pub async fn run(self, settings: &Settings, db: &mut impl Database) -> Result<()> {
+        match self {
+            Self::Create { file } => create(settings, db, &file).await,
+            Self::Restore { file } => restore(settings, db, &file).await,
+        }
+    }
+}
+
+/// Load the existing encryption key. `load_key` would create a new one, which
+/// could never open a backup made elsewhere, and would leave a new backup
+/// sealed with a key nobody has kept.
+fn existing_key(settings: &Settings, hint: &str) -> Result<Key> {
+    if !Path::new(&settings.key_path).exists() {
+        bail!("No encryption key found at {}. {hint}", settings.key_path);
+    }
+    load_key(settings).wrap_err("could not load encryption key")
+}
+
+async fn create(settings: &Settings, db: &impl Database, file: &Path) -> Result<()> {
+    let key = existing_key(
+        settings,
+        "Run `atuin key` to create one, and keep it safe: the backup can only be restored with it",
+    )?;
+
+    let backup = Backup::create(db).await?;
+    let bytes = backup.encrypt(&key)?;
+
+    // encrypted, but still only for the owner to read
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    fs::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut f = options.open(file)?;
+    #[cfg(unix)]
+    f.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    f.write_all(&bytes)?;
+
+    println!(
+        "Backed up {} entries to {}",
+        backup.history.len(),
+        file.display()
+    );
+    Ok(())
+}
+
+async fn restore(settings: &Settings, db: &mut impl Database, file: &Path) -> Result<()> {
+    let key = existing_key(
+        settings,
+        "Copy the key of the machine the backup was created on there first",
+    )?;
+
+    let backup = Backup::decrypt(&fs::read(file)?, &key)
+        .wrap_err_with(|| format!("could not read backup {}", file.display()))?;
+    let total = backup.history.len();
+
+    let before = db.history_count().await?;
+    backup.restore(db).await?;
+    let added = db.history_count().await? - before;
+
+    println!("Restore complete! Added {added} of the {total} entries in the backup");
+    Ok(())
+}
@@ -1,26 +1,51 @@
 use std::{
+    collections::HashSet,
     env,
     fmt::{self, Display},
     io::{StdoutLock, Write},
     time::Duration,
 };
 
-use atuin_common::utils;
-use clap::Subcommand;
-use eyre::Result;
+use atuin_common::utils::{self, uuid_v4};
+use chrono::{
+    format::{Item, StrftimeItems},
+    Local, Utc,
+};
+use clap::{Subcommand, ValueEnum};
+use eyre::{Result, WrapErr};
+use itertools::Itertools;
 use runtime_format::{FormatKey, FormatKeyError, ParsedFmt};
+use serde::Serialize;
 
 use atuin_client::{
-    database::{current_context, Database},
+    database::{current_context, Database, SearchFilter},
+    export,
     history::History,
-    settings::Settings,
+    secrets::{self, Outcome},
+    settings::{SecretPolicy, Settings},
 };
 
 #[cfg(feature = "sync")]
 use atuin_client::sync;
 use log::debug;
 
-use super::search::format_duration_into;
+use super::search::{format_duration_into, FilterArgs};
+
+/// All keys understood by `FmtHistory`, for the error message when an unknown one is used
+const FORMAT_KEYS: &[&str] = &[
+    "command",
+    "directory",
+    "duration",
+    "user",
+    "host",
+    "time",
+    "time:<strftime>",
+    "relativetime",
+    "exit",
+    "success",
+    "session",
+    "id",
+];
 
 #[derive(Subcommand)]
 #[command(infer_subcommands = true)]
@@ -50,10 +75,15 @@ pub enum Cmd {
         #[arg(long)]
         cmd_only: bool,
 
-        /// Available variables: {command}, {directory}, {duration}, {user}, {host} and {time}.
+        /// Available variables: {command}, {directory}, {duration}, {user}, {host}, {time}, {time:<strftime>},
+        /// {relativetime}, {exit}, {success}, {session} and {id}.
         /// Example: --format "{time} - [{duration}] - {directory}$\t{command}"
         #[arg(long, short)]
         format: Option<String>,
+
+        /// Output machine-readable records instead of formatted text
+        #[arg(long, value_enum, conflicts_with_all = ["human", "cmd_only", "format"])]
+        output: Option<OutputFormat>,
     },
 
     /// Get the last command ran
@@ -65,11 +95,72 @@ pub enum Cmd {
         #[arg(long)]
         cmd_only: bool,
 
-        /// Available variables: {command}, {directory}, {duration}, {user}, {host} and {time}.
+        /// Available variables: {command}, {directory}, {duration}, {user}, {host}, {time}, {time:<strftime>},
+        /// {relativetime}, {exit}, {success}, {session} and {id}.
         /// Example: --format "{time} - [{duration}] - {directory}$\t{command}"
         #[arg(long, short)]
         format: Option<String>,
+
+        /// Output machine-readable records instead of formatted text
+        #[arg(long, value_enum, conflicts_with_all = ["human", "cmd_only", "format"])]
+        output: Option<OutputFormat>,
+    },
+
+    /// Write history out in a shell's history format, or as JSON or CSV
+    Export {
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+
+        #[command(flatten)]
+        filter: Box<FilterArgs>,
+    },
+
+    /// Remove history that would not be saved with the current settings,
+    /// because it matches `history_filter` or contains a secret
+    Prune {
+        /// List what would be removed, without removing it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Instead, remove history that is older than this (eg: 90d), unless it is pinned
+        #[arg(long, value_parser = parse_age)]
+        older_than: Option<Duration>,
     },
+
+    /// Keep entries from ever being expired by `history_retention`.
+    /// Lists the pinned entries if no ids are given
+    Pin { ids: Vec<String> },
+
+    /// Let entries be expired again
+    Unpin { ids: Vec<String> },
+}
+
+fn parse_age(s: &str) -> Result<Duration, String> {
+    parse_duration::parse(s).map_err(|e| e.to_string())
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum OutputFormat {
+    /// A single JSON array of entries
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// Comma separated values, with a header row
+    Csv,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportFormat {
+    /// zsh's extended history, with start times and durations
+    Zsh,
+    /// bash's history, with `#` timestamp lines
+    Bash,
+    /// fish's history file
+    Fish,
+    /// A single JSON array of entries, the same as `history list --output json`
+    Json,
+    /// Comma separated values, the same as `history list --output csv`
+    Csv,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -77,11 +168,14 @@ pub enum ListMode {
     Human,
     CmdOnly,
     Regular,
+    Output(OutputFormat),
 }
 
 impl ListMode {
-    pub const fn from_flags(human: bool, cmd_only: bool) -> Self {
-        if human {
+    pub const fn from_flags(human: bool, cmd_only: bool, output: Option<OutputFormat>) -> Self {
+        if let Some(output) = output {
+            ListMode::Output(output)
+        } else if human {
             ListMode::Human
         } else if cmd_only {
             ListMode::CmdOnly
@@ -100,6 +194,7 @@ pub fn print_list(h: &[History], list_mode: ListMode, format: Option<&str>) {
         ListMode::Human => print_human_list(&mut w, h, format),
         ListMode::CmdOnly => print_cmd_only(&mut w, h),
         ListMode::Regular => print_regular(&mut w, h, format),
+        ListMode::Output(output) => print_output(&mut w, h, output),
     }
 
     w.flush().expect("failed to flush history");
@@ -120,6 +215,12 @@ impl FormatKey for FmtHistory<'_> {
                 format_duration_into(dur, f)?;
             }
             "time" => self.0.timestamp.format("%Y-%m-%d %H:%M:%S").fmt(f)?,
+            "relativetime" => {
+                // timestamps "in the future" are shown as 0s ago
+                let since = Utc::now() - self.0.timestamp;
+                format_duration_into(since.to_std().unwrap_or_default(), f)?;
+                f.write_str(" ago")?;
+            }
             "host" => f.write_str(
                 self.0
                     .hostname
@@ -127,12 +228,43 @@ impl FormatKey for FmtHistory<'_> {
                     .map_or(&self.0.hostname, |(host, _)| host),
             )?,
             "user" => f.write_str(self.0.hostname.split_once(':').map_or("", |(_, user)| user))?,
-            _ => return Err(FormatKeyError::UnknownKey),
+            "exit" => self.0.exit.fmt(f)?,
+            "success" => f.write_str(if self.0.success() { "✓" } else { "✗" })?,
+            "session" => f.write_str(&self.0.session)?,
+            "id" => f.write_str(&self.0.id)?,
+            _ => match key.strip_prefix("time:") {
+                Some(pattern) => self
+                    .0
+                    .timestamp
+                    .with_timezone(&Local)
+                    .format(pattern)
+                    .fmt(f)?,
+                None => return Err(FormatKeyError::UnknownKey),
+            },
         }
         Ok(())
     }
 }
 
+/// Check every key in the format up front, so we can give a helpful error
+/// rather than failing halfway through the output
+fn check_format_keys(fmt: &ParsedFmt) -> Result<(), String> {
+    for key in fmt.keys() {
+        if let Some(pattern) = key.strip_prefix("time:") {
+            if StrftimeItems::new(pattern).any(|i| matches!(i, Item::Error)) {
+                return Err(format!("invalid time format {pattern:?} in {{{key}}}"));
+            }
+        } else if !FORMAT_KEYS.contains(&key) {
+            let valid = FORMAT_KEYS.iter().map(|k| format!("{{{k}}}")).join(", ");
+            return Err(format!(
+                "unknown format key {{{key}}}. Valid keys are: {valid}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 fn print_list_with(w: &mut StdoutLock, h: &[History], format: &str) {
     let fmt = match ParsedFmt::new(format) {
         Ok(fmt) => fmt,
@@ -143,6 +275,11 @@ fn print_list_with(w: &mut StdoutLock, h: &[History], format: &str) {
         }
     };
 
+    if let Err(err) = check_format_keys(&fmt) {
+        eprintln!("ERROR: History formatting failed with the following error: {err}");
+        std::process::exit(1)
+    }
+
     for h in h.iter().rev() {
         writeln!(w, "{}", fmt.with_args(&FmtHistory(h))).expect("failed to write history");
     }
@@ -168,7 +305,200 @@ pub fn print_cmd_only(w: &mut StdoutLock, h: &[History]) {
     }
 }
 
+/// The stable, machine-readable representation of a history entry.
+/// This is separate from `History` so that changes to the sync format do not break scripts.
+#[derive(Serialize)]
+struct HistoryRecord<'a> {
+    id: &'a str,
+    timestamp: String,
+    duration: i64,
+    exit: i64,
+    command: &'a str,
+    cwd: &'a str,
+    session: &'a str,
+    hostname: &'a str,
+}
+
+impl<'a> From<&'a History> for HistoryRecord<'a> {
+    fn from(h: &'a History) -> Self {
+        Self {
+            id: &h.id,
+            timestamp: h.timestamp.to_rfc3339(),
+            duration: h.duration,
+            exit: h.exit,
+            command: &h.command,
+            cwd: &h.cwd,
+            session: &h.session,
+            hostname: &h.hostname,
+        }
+    }
+}
+
+const CSV_HEADER: &str = "id,timestamp,duration,exit,command,cwd,session,hostname";
+
+fn csv_field(field: &str) -> std::borrow::Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+fn write_output(w: &mut impl Write, h: &[History], output: OutputFormat) -> std::io::Result<()> {
+    let records = h.iter().rev().map(HistoryRecord::from);
+
+    match output {
+        OutputFormat::Json => {
+            serde_json::to_writer(&mut *w, &records.collect::<Vec<_>>())?;
+            writeln!(w)?;
+        }
+        OutputFormat::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut *w, &record)?;
+                writeln!(w)?;
+            }
+        }
+        OutputFormat::Csv => {
+            writeln!(w, "{CSV_HEADER}")?;
+            for r in records {
+                writeln!(
+                    w,
+                    "{},{},{},{},{},{},{},{}",
+                    csv_field(r.id),
+                    r.timestamp,
+                    r.duration,
+                    r.exit,
+                    csv_field(r.command),
+                    csv_field(r.cwd),
+                    csv_field(r.session),
+                    csv_field(r.hostname),
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn print_output(w: &mut StdoutLock, h: &[History], output: OutputFormat) {
+    write_output(w, h, output).expect("failed to write history");
+}
+
+/// What pruning does to the history that should not have been saved
+#[derive(Default)]
+struct PrunePlan {
+    delete: Vec<History>,
+    /// Redacted copies to save in place of some of the deleted entries
+    redacted: Vec<History>,
+    /// Copies to save local only, in place of deleted entries that were synced
+    local_only: Vec<History>,
+}
+
+/// Split out the history that should not have been saved. `local_only` are the
+/// ids of the history that is already never synced
+fn plan_prune(
+    history: Vec<History>,
+    policy: SecretPolicy,
+    local_only: &HashSet<String>,
+    is_filtered: impl Fn(&str) -> bool,
+) -> PrunePlan {
+    let mut plan = PrunePlan::default();
+
+    for h in history {
+        if is_filtered(&h.command) {
+            plan.delete.push(h);
+            continue;
+        }
+
+        // the copies get a new id, as the old one will be deleted everywhere it
+        // has been synced to
+        match secrets::check(policy, h.command.clone()) {
+            Outcome::Drop => plan.delete.push(h),
+            Outcome::Save(command) if command != h.command => {
+                plan.redacted.push(History {
+                    id: uuid_v4(),
+                    command,
+                    ..h.clone()
+                });
+                plan.delete.push(h);
+            }
+            Outcome::SaveLocal(_) if !local_only.contains(&h.id) => {
+                plan.local_only.push(History {
+                    id: uuid_v4(),
+                    ..h.clone()
+                });
+                plan.delete.push(h);
+            }
+            Outcome::Save(_) | Outcome::SaveLocal(_) => {}
+        }
+    }
+
+    plan
+}
+
+async fn prune(
+    settings: &Settings,
+    db: &mut impl Database,
+    dry_run: bool,
+    older_than: Option<Duration>,
+) -> Result<()> {
+    let plan = if let Some(age) = older_than {
+        let cutoff = Utc::now() - chrono::Duration::from_std(age)?;
+        PrunePlan {
+            delete: db.expired(Some(cutoff), None).await?,
+            ..PrunePlan::default()
+        }
+    } else {
+        let history = db.query_history(&SearchFilter::default()).await?;
+        let local_only = db.local_only_ids().await?.into_iter().collect();
+        plan_prune(history, settings.secret_policy, &local_only, |c| {
+            settings.is_filtered(c)
+        })
+    };
+
+    let note = |n: usize, copy: &str| {
+        if n == 0 {
+            String::new()
+        } else {
+            format!(", replacing {n} with {copy}")
+        }
+    };
+    let note = note(plan.redacted.len(), "a redacted copy")
+        + &note(plan.local_only.len(), "a copy that is never synced");
+
+    if dry_run {
+        print_list(&plan.delete, ListMode::Regular, Some("{time}\t{command}"));
+        println!("Would remove {} entries{note}", plan.delete.len());
+        return Ok(());
+    }
+
+    // deleting records an event for each entry, so sync removes them from the
+    // server and other machines too
+    db.delete_bulk(&plan.delete).await?;
+    db.save_bulk(&plan.redacted).await?;
+    for h in &plan.local_only {
+        db.save_local_only(h).await?;
+    }
+    println!("Removed {} entries{note}", plan.delete.len());
+
+    Ok(())
+}
+
+async fn pin(db: &impl Database, ids: &[String]) -> Result<()> {
+    // make sure there is something to pin, before pinning any of it
+    for id in ids {
+        db.load(id)
+            .await
+            .wrap_err_with(|| format!("no history with id {id}"))?;
+    }
+    for id in ids {
+        db.pin(id).await?;
+    }
+    Ok(())
+}
+
 impl Cmd {
+    #[allow(clippy::too_many_lines)]
     pub async fn run(&self, settings: &Settings, db: &mut impl Database) -> Result<()> {
         let context = current_context();
 
@@ -176,10 +506,16 @@ impl Cmd {
             Self::Start { command: words } => {
                 let command = words.join(" ");
 
-                if command.starts_with(' ') || settings.history_filter.is_match(&command) {
+                if settings.is_filtered(&command) {
                     return Ok(());
                 }
 
+                let (command, local_only) = match secrets::check(settings.secret_policy, command) {
+                    Outcome::Save(command) => (command, false),
+                    Outcome::SaveLocal(command) => (command, true),
+                    Outcome::Drop => return Ok(()),
+                };
+
                 // It's better for atuin to silently fail here and attempt to
                 // store whatever is ran, than to throw an error to the terminal
                 let cwd = utils::get_current_dir();
@@ -189,7 +525,11 @@ impl Cmd {
                 // print the ID
                 // we use this as the key for calling end
                 println!("{}", h.id);
-                db.save(&h).await?;
+                if local_only {
+                    db.save_local_only(&h).await?;
+                } else {
+                    db.save(&h).await?;
+                }
                 Ok(())
             }
 
@@ -212,6 +552,20 @@ impl Cmd {
 
                 db.update(&h).await?;
 
+                if settings.should_expire()? {
+                    let expired = db
+                        .expired(
+                            settings.history_retention.cutoff(),
+                            settings.history_retention.max_entries,
+                        )
+                        .await?;
+                    debug!("expiring {} entries", expired.len());
+                    // this records a delete for each, which sync sends on to the
+                    // server and other machines
+                    db.delete_bulk(&expired).await?;
+                    Settings::save_expire_time()?;
+                }
+
                 if settings.should_sync()? {
                     #[cfg(feature = "sync")]
                     {
@@ -233,6 +587,7 @@ impl Cmd {
                 human,
                 cmd_only,
                 format,
+                output,
             } => {
                 let session = if *session {
                     Some(env::var("ATUIN_SESSION")?)
@@ -247,25 +602,19 @@ impl Cmd {
 
                 let history = match (session, cwd) {
                     (None, None) => db.list(settings.filter_mode, &context, None, false).await?,
-                    (None, Some(cwd)) => {
-                        let query = format!("select * from history where cwd = '{cwd}';");
-                        db.query_history(&query).await?
-                    }
-                    (Some(session), None) => {
-                        let query = format!("select * from history where session = '{session}';");
-                        db.query_history(&query).await?
-                    }
-                    (Some(session), Some(cwd)) => {
-                        let query = format!(
-                            "select * from history where cwd = '{cwd}' and session = '{session}';",
-                        );
-                        db.query_history(&query).await?
+                    (session, cwd) => {
+                        let filter_options = SearchFilter {
+                            cwd,
+                            session,
+                            ..SearchFilter::default()
+                        };
+                        db.query_history(&filter_options).await?
                     }
                 };
 
                 print_list(
                     &history,
-                    ListMode::from_flags(*human, *cmd_only),
+                    ListMode::from_flags(*human, *cmd_only, *output),
                     format.as_deref(),
                 );
 
@@ -276,16 +625,215 @@ impl Cmd {
                 human,
                 cmd_only,
                 format,
+                output,
             } => {
                 let last = db.last().await?;
                 print_list(
                     &[last],
-                    ListMode::from_flags(*human, *cmd_only),
+                    ListMode::from_flags(*human, *cmd_only, *output),
                     format.as_deref(),
                 );
 
                 Ok(())
             }
+
+            Self::Export { format, filter } => {
+                let history = db.query_history(&filter.filter_options()).await?;
+
+                let w = std::io::stdout();
+                let mut w = w.lock();
+                match format {
+                    ExportFormat::Zsh => export::write_zsh(&mut w, &history)?,
+                    ExportFormat::Bash => export::write_bash(&mut w, &history)?,
+                    ExportFormat::Fish => export::write_fish(&mut w, &history)?,
+                    ExportFormat::Json => write_output(&mut w, &history, OutputFormat::Json)?,
+                    ExportFormat::Csv => write_output(&mut w, &history, OutputFormat::Csv)?,
+                }
+                w.flush()?;
+
+                Ok(())
+            }
+
+            Self::Prune {
+                dry_run,
+                older_than,
+            } => prune(settings, db, *dry_run, *older_than).await,
+
+            Self::Pin { ids } if ids.is_empty() => {
+                let pinned = db.pinned().await?;
+                print_list(&pinned, ListMode::Human, None);
+                Ok(())
+            }
+
+            Self::Pin { ids } => pin(db, ids).await,
+
+            Self::Unpin { ids } => {
+                for id in ids {
+                    db.unpin(id).await?;
+                }
+                Ok(())
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use runtime_format::ParsedFmt;
+
+    use atuin_client::{
+        database::{Database, Sqlite},
+        settings::SecretPolicy,
+    };
+
+    use super::super::import::tests::{filter_history, filter_settings};
+    use super::{
+        check_format_keys, pin, plan_prune, write_output, FmtHistory, HashSet, History,
+        OutputFormat,
+    };
+
+    fn history() -> Vec<History> {
+        let mut h = History::new(
+            Utc.timestamp(1_600_000_000, 0),
+            "echo \"a, b\"\n\tls".to_string(),
+            "/home/ellie".to_string(),
+            0,
+            1_000,
+            Some("session".to_string()),
+            Some("host:ellie".to_string()),
+        );
+        h.id = "id".to_string();
+        vec![h]
+    }
+
+    fn output(format: OutputFormat) -> String {
+        let mut w = Vec::new();
+        write_output(&mut w, &history(), format).unwrap();
+        String::from_utf8(w).unwrap()
+    }
+
+    fn format(format: &str) -> String {
+        let fmt = ParsedFmt::new(format).unwrap();
+        check_format_keys(&fmt).unwrap();
+        fmt.with_args(&FmtHistory(&history()[0])).to_string()
+    }
+
+    #[test]
+    fn format_keys() {
+        assert_eq!(
+            format("{id} {session} {host} {user} {exit} {success}"),
+            "id session host ellie 0 ✓"
+        );
+        assert_eq!(format("{time:%Y}"), "2020");
+        assert!(format("{relativetime}").ends_with("y ago"));
+    }
+
+    #[test]
+    fn format_keys_invalid() {
+        let fmt = ParsedFmt::new("{command} {nope}").unwrap();
+        let err = check_format_keys(&fmt).unwrap_err();
+        assert!(err.starts_with("unknown format key {nope}. Valid keys are: {command},"));
+
+        let fmt = ParsedFmt::new("{time:%Q}").unwrap();
+        assert!(check_format_keys(&fmt).is_err());
+    }
+
+    #[test]
+    fn output_ndjson() {
+        assert_eq!(
+            output(OutputFormat::Ndjson),
+            "{\"id\":\"id\",\"timestamp\":\"2020-09-13T12:26:40+00:00\",\"duration\":1000,\"exit\":0,\"command\":\"echo \\\"a, b\\\"\\n\\tls\",\"cwd\":\"/home/ellie\",\"session\":\"session\",\"hostname\":\"host:ellie\"}\n"
+        );
+    }
+
+    #[test]
+    fn output_csv() {
+        assert_eq!(
+            output(OutputFormat::Csv),
+            "id,timestamp,duration,exit,command,cwd,session,hostname\nid,2020-09-13T12:26:40+00:00,1000,0,\"echo \"\"a, b\"\"\n\tls\",/home/ellie,session,host:ellie\n"
+        );
+    }
+
+    #[test]
+    fn prune() {
+        let history = filter_history();
+        let settings = filter_settings();
+        let is_filtered = |c: &str| settings.is_filtered(c);
+        let commands = |h: &[History]| h.iter().map(|h| h.command.clone()).collect::<Vec<_>>();
+
+        let local_only = HashSet::new();
+
+        let plan = plan_prune(
+            history.clone(),
+            SecretPolicy::Drop,
+            &local_only,
+            is_filtered,
+        );
+        assert_eq!(
+            commands(&plan.delete),
+            [
+                " echo hidden",
+                "secret-cmd --flag",
+                "mysql --password=hunter2"
+            ]
+        );
+        assert!(plan.redacted.is_empty());
+
+        let plan = plan_prune(
+            history.clone(),
+            SecretPolicy::Redact,
+            &local_only,
+            is_filtered,
+        );
+        assert_eq!(plan.delete.len(), 3);
+        assert_eq!(commands(&plan.redacted), ["mysql --password=[REDACTED]"]);
+        assert_ne!(plan.redacted[0].id, history[3].id);
+        assert_eq!(plan.redacted[0].timestamp, history[3].timestamp);
+
+        // the synced secret moves to a local-only copy
+        let plan = plan_prune(
+            history.clone(),
+            SecretPolicy::LocalOnly,
+            &local_only,
+            is_filtered,
+        );
+        assert_eq!(plan.delete.len(), 3);
+        assert!(plan.redacted.is_empty());
+        assert_eq!(commands(&plan.local_only), ["mysql --password=hunter2"]);
+        assert_ne!(plan.local_only[0].id, history[3].id);
+
+        // unless it is already local only
+        let local_only = HashSet::from([history[3].id.clone()]);
+        let plan = plan_prune(history, SecretPolicy::LocalOnly, &local_only, is_filtered);
+        assert_eq!(plan.delete.len(), 2);
+        assert!(plan.local_only.is_empty());
+    }
+
+    async fn pinned(db: &Sqlite) -> Vec<String> {
+        let pinned = db.pinned().await.unwrap();
+        pinned.into_iter().map(|h| h.id).collect()
+    }
+
+    #[tokio::test]
+    async fn pin_unpin() {
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        let h = history().remove(0);
+        db.save(&h).await.unwrap();
+
+        // an unknown id pins nothing at all
+        assert!(pin(&db, &["id".to_string(), "nope".to_string()])
+            .await
+            .is_err());
+        assert!(pinned(&db).await.is_empty());
+
+        pin(&db, &["id".to_string()]).await.unwrap();
+        pin(&db, &["id".to_string()]).await.unwrap();
+        assert_eq!(pinned(&db).await, ["id"]);
+
+        db.unpin("id").await.unwrap();
+        db.unpin("nope").await.unwrap();
+        assert!(pinned(&db).await.is_empty());
+    }
+}
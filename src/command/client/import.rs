@@ -1,40 +1,107 @@
-use std::env;
+use std::{collections::HashSet, convert::TryFrom, env, path::PathBuf};
 
 use async_trait::async_trait;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use eyre::Result;
+use fs_err as fs;
 use indicatif::ProgressBar;
 
 use atuin_client::{
     database::Database,
     history::History,
     import::{
-        bash::Bash, fish::Fish, resh::Resh, zsh::Zsh, zsh_histdb::ZshHistDb, Importer, Loader,
+        atuin_db::AtuinDb, bash::Bash, fish::Fish, mcfly::Mcfly, nu::Nu, nu_histdb::NuHistDb,
+        resh::Resh, zsh::Zsh, zsh_histdb::ZshHistDb, Importer, Loader,
     },
+    secrets::{self, Outcome},
+    settings::Settings,
 };
 
 #[derive(Parser)]
+pub struct Cmd {
+    #[command(subcommand)]
+    source: Source,
+
+    /// Only report how many entries would be imported, without importing them
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Import everything, even commands that start with a space, match `history_filter`
+    /// or contain secrets
+    #[arg(long, global = true)]
+    no_filter: bool,
+}
+
+#[derive(Subcommand)]
 #[command(infer_subcommands = true)]
-pub enum Cmd {
+pub enum Source {
     /// Import history for the current shell
-    Auto,
+    Auto {
+        /// Read this file instead of looking in the default location
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
 
     /// Import history from the zsh history file
-    Zsh,
+    Zsh {
+        /// Read this file instead of looking in the default location
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
     /// Import history from the zsh history file
-    ZshHistDb,
+    ZshHistDb {
+        /// Read this database instead of looking in the default location
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
     /// Import history from the bash history file
-    Bash,
+    Bash {
+        /// Read this file instead of looking in the default location
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
     /// Import history from the resh history file
-    Resh,
+    Resh {
+        /// Read this file instead of looking in the default location
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
     /// Import history from the fish history file
-    Fish,
+    Fish {
+        /// Read this file instead of looking in the default location
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Import history from the nushell plaintext history file
+    Nu {
+        /// Read this file instead of looking in the default location
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Import history from the nushell sqlite history database
+    NuHistDb {
+        /// Read this database instead of looking in the default location
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Import history from the mcfly history database
+    Mcfly {
+        /// Read this database instead of looking in the default location
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Import history from another atuin database, skipping anything already here
+    AtuinDb {
+        /// The database to read
+        #[arg(long)]
+        file: PathBuf,
+    },
 }
 
 const BATCH_SIZE: usize = 100;
 
 impl Cmd {
-    pub async fn run<DB: Database>(&self, db: &mut DB) -> Result<()> {
+    pub async fn run<DB: Database>(&self, settings: &Settings, db: &mut DB) -> Result<()> {
         println!("        Atuin         ");
         println!("======================");
         println!("          \u{1f30d}          ");
@@ -43,8 +110,14 @@ impl Cmd {
         println!("======================");
         println!("Importing history...");
 
-        match self {
-            Self::Auto => {
+        let opts = ImportOptions {
+            dry_run: self.dry_run,
+            filter: (!self.no_filter).then(|| settings),
+        };
+        match &self.source {
+            Source::Auto { file } => {
+                let file = file.clone();
+
                 if cfg!(windows) {
                     println!("This feature does not work on windows. Please run atuin import <SHELL>. To view a list of shells, run atuin import.");
                     return Ok(());
@@ -52,65 +125,145 @@ impl Cmd {
 
                 let shell = env::var("SHELL").unwrap_or_else(|_| String::from("NO_SHELL"));
                 if shell.ends_with("/zsh") {
-                    if ZshHistDb::histpath().is_ok() {
+                    // an explicit file is far more likely to be a plain history file
+                    if file.is_none() && ZshHistDb::histpath().is_ok() {
                         println!(
                             "Detected Zsh-HistDb, using :{}",
                             ZshHistDb::histpath().unwrap().to_str().unwrap()
                         );
-                        import::<ZshHistDb, DB>(db).await
+                        import::<ZshHistDb, DB>(db, file, &opts).await
                     } else {
                         println!("Detected ZSH");
-                        import::<Zsh, DB>(db).await
+                        import::<Zsh, DB>(db, file, &opts).await
                     }
                 } else if shell.ends_with("/fish") {
                     println!("Detected Fish");
-                    import::<Fish, DB>(db).await
+                    import::<Fish, DB>(db, file, &opts).await
                 } else if shell.ends_with("/bash") {
                     println!("Detected Bash");
-                    import::<Bash, DB>(db).await
+                    import::<Bash, DB>(db, file, &opts).await
+                } else if shell.ends_with("/nu") {
+                    // nushell keeps one or the other, depending on its history.file_format
+                    let sqlite = file.as_ref().map_or_else(
+                        || NuHistDb::histpath().is_ok(),
+                        |f| f.extension().map_or(false, |e| e == "sqlite3"),
+                    );
+                    if sqlite {
+                        println!("Detected Nushell, using the sqlite history");
+                        import::<NuHistDb, DB>(db, file, &opts).await
+                    } else {
+                        println!("Detected Nushell");
+                        import::<Nu, DB>(db, file, &opts).await
+                    }
                 } else {
                     println!("cannot import {shell} history");
                     Ok(())
                 }
             }
 
-            Self::Zsh => import::<Zsh, DB>(db).await,
-            Self::ZshHistDb => import::<ZshHistDb, DB>(db).await,
-            Self::Bash => import::<Bash, DB>(db).await,
-            Self::Resh => import::<Resh, DB>(db).await,
-            Self::Fish => import::<Fish, DB>(db).await,
+            Source::Zsh { file } => import::<Zsh, DB>(db, file.clone(), &opts).await,
+            Source::ZshHistDb { file } => import::<ZshHistDb, DB>(db, file.clone(), &opts).await,
+            Source::Bash { file } => import::<Bash, DB>(db, file.clone(), &opts).await,
+            Source::Resh { file } => import::<Resh, DB>(db, file.clone(), &opts).await,
+            Source::Fish { file } => import::<Fish, DB>(db, file.clone(), &opts).await,
+            Source::Nu { file } => import::<Nu, DB>(db, file.clone(), &opts).await,
+            Source::NuHistDb { file } => import::<NuHistDb, DB>(db, file.clone(), &opts).await,
+            Source::Mcfly { file } => import::<Mcfly, DB>(db, file.clone(), &opts).await,
+            Source::AtuinDb { file } => import::<AtuinDb, DB>(db, Some(file.clone()), &opts).await,
         }
     }
 }
 
-pub struct HistoryImporter<'db, DB: Database> {
+/// How the entries read by an importer are treated
+struct ImportOptions<'s> {
+    dry_run: bool,
+    /// The settings to filter the commands and find secrets with, unless that is turned off
+    filter: Option<&'s Settings>,
+}
+
+pub struct HistoryImporter<'db, 'o, DB: Database> {
     pb: ProgressBar,
     buf: Vec<History>,
     db: &'db mut DB,
+    opts: &'o ImportOptions<'o>,
+    count: usize,
+    filtered: usize,
+    duplicates: usize,
+    /// What a dry run would have saved so far, as (timestamp, cwd, command),
+    /// to find the duplicates within the history being imported
+    seen: HashSet<(i64, String, String)>,
 }
 
-impl<'db, DB: Database> HistoryImporter<'db, DB> {
-    fn new(db: &'db mut DB, len: usize) -> Self {
+impl<'db, 'o, DB: Database> HistoryImporter<'db, 'o, DB> {
+    fn new(db: &'db mut DB, len: usize, opts: &'o ImportOptions<'o>) -> Self {
         Self {
             pb: ProgressBar::new(len as u64),
             buf: Vec::with_capacity(BATCH_SIZE),
             db,
+            opts,
+            count: 0,
+            filtered: 0,
+            duplicates: 0,
+            seen: HashSet::new(),
         }
     }
 
-    async fn flush(self) -> Result<()> {
-        if !self.buf.is_empty() {
+    /// Save what is left, and return how many entries were kept, how many filtered
+    /// out, and, for a dry run, how many are already saved
+    async fn flush(self) -> Result<(usize, usize, usize)> {
+        if !self.buf.is_empty() && !self.opts.dry_run {
             self.db.save_bulk(&self.buf).await?;
         }
         self.pb.finish();
-        Ok(())
+        Ok((self.count, self.filtered, self.duplicates))
     }
 }
 
 #[async_trait]
-impl<'db, DB: Database> Loader for HistoryImporter<'db, DB> {
-    async fn push(&mut self, hist: History) -> Result<()> {
+impl<DB: Database> Loader for HistoryImporter<'_, '_, DB> {
+    async fn push(&mut self, mut hist: History) -> Result<()> {
         self.pb.inc(1);
+
+        let mut local_only = false;
+        if let Some(settings) = self.opts.filter {
+            if settings.is_filtered(&hist.command) {
+                self.filtered += 1;
+                return Ok(());
+            }
+
+            match secrets::check(settings.secret_policy, hist.command) {
+                Outcome::Save(command) => hist.command = command,
+                Outcome::SaveLocal(command) => {
+                    hist.command = command;
+                    local_only = true;
+                }
+                Outcome::Drop => {
+                    self.filtered += 1;
+                    return Ok(());
+                }
+            }
+        }
+
+        if self.opts.dry_run {
+            let key = (
+                hist.timestamp.timestamp_nanos(),
+                hist.cwd.clone(),
+                hist.command.clone(),
+            );
+            if self.seen.insert(key) && !self.db.exists(&hist).await? {
+                self.count += 1;
+            } else {
+                self.duplicates += 1;
+            }
+            return Ok(());
+        }
+
+        self.count += 1;
+
+        if local_only {
+            return self.db.save_local_only(&hist).await.map_err(Into::into);
+        }
+
         self.buf.push(hist);
         if self.buf.len() == self.buf.capacity() {
             self.db.save_bulk(&self.buf).await?;
@@ -120,15 +273,175 @@ impl<'db, DB: Database> Loader for HistoryImporter<'db, DB> {
     }
 }
 
-async fn import<I: Importer + Send, DB: Database>(db: &mut DB) -> Result<()> {
+async fn import<I: Importer + Send, DB: Database>(
+    db: &mut DB,
+    file: Option<PathBuf>,
+    opts: &ImportOptions<'_>,
+) -> Result<()> {
     println!("Importing history from {}", I::NAME);
 
-    let mut importer = I::new().await?;
-    let len = importer.entries().await.unwrap();
-    let mut loader = HistoryImporter::new(db, len);
+    let path = I::locate(file)?;
+    // however the history was found, it is the same source
+    let source = format!(
+        "{}:{}",
+        I::NAME,
+        fs::canonicalize(&path)
+            .unwrap_or_else(|_| path.clone())
+            .display()
+    );
+    let mut importer = I::new(path).await?;
+
+    let watermark = importer.watermark();
+    let mut skipped = 0;
+    if let Some(previous) = db.import_watermark(&source).await? {
+        match importer.resume(&previous).await? {
+            Some(already) => skipped = already,
+            None => println!(
+                "The history has changed since it was last imported, reading all of it again"
+            ),
+        }
+    }
+    let invalid = importer.invalid();
+
+    let before = db.history_count().await?;
+    let len = importer.entries().await?;
+    let mut loader = HistoryImporter::new(db, len, opts);
     importer.load(&mut loader).await?;
-    loader.flush().await?;
+    let (pushed, filtered, duplicates) = loader.flush().await?;
 
-    println!("Import complete!");
+    if opts.dry_run {
+        let duplicates = skipped + duplicates;
+        println!(
+            "Would add {pushed} entries, skip {duplicates} duplicates, filter out {filtered} and reject {invalid} that are not valid UTF-8"
+        );
+        return Ok(());
+    }
+
+    db.save_import_watermark(&source, &watermark).await?;
+
+    // anything the database ignored was already there too
+    let added = usize::try_from(db.history_count().await? - before).unwrap_or(0);
+    let duplicates = skipped + pushed.saturating_sub(added);
+    println!("Import complete! Added {added} entries, skipped {duplicates} duplicates, filtered out {filtered} and rejected {invalid} that are not valid UTF-8");
     Ok(())
 }
+
+#[cfg(test)]
+pub mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use atuin_client::database::{SearchFilter, Sqlite};
+
+    use super::*;
+
+    /// Settings that filter out `secret-cmd` and redact secrets, without reading
+    /// the user's config
+    pub fn filter_settings() -> Settings {
+        let config = "history_filter = [\"^secret-cmd\"]\nsecret_policy = \"redact\"";
+        Settings::from_toml(&env::temp_dir().join("atuin-filter-test"), config).unwrap()
+    }
+
+    /// One entry that is kept, one hidden with a leading space, one matching
+    /// `filter_settings`'s `history_filter` and one with a secret, oldest first
+    pub fn filter_history() -> Vec<History> {
+        let commands = [
+            "ls",
+            " echo hidden",
+            "secret-cmd --flag",
+            "mysql --password=hunter2",
+        ];
+
+        (1_600_000_000..)
+            .zip(commands)
+            .map(|(timestamp, command)| {
+                History::new(
+                    Utc.timestamp(timestamp, 0),
+                    command.to_string(),
+                    "/".to_string(),
+                    0,
+                    1,
+                    Some("session".to_string()),
+                    Some("host:ellie".to_string()),
+                )
+            })
+            .collect()
+    }
+
+    async fn import(db: &mut Sqlite, opts: &ImportOptions<'_>) -> (usize, usize, usize) {
+        let history = filter_history();
+
+        let mut loader = HistoryImporter::new(db, history.len(), opts);
+        for h in history {
+            loader.push(h).await.unwrap();
+        }
+        loader.flush().await.unwrap()
+    }
+
+    async fn saved(db: &Sqlite) -> Vec<String> {
+        let mut saved: Vec<String> = db
+            .query_history(&SearchFilter::default())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|h| h.command)
+            .collect();
+        saved.sort();
+        saved
+    }
+
+    #[tokio::test]
+    async fn filter() {
+        let settings = filter_settings();
+
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        let opts = ImportOptions {
+            dry_run: false,
+            filter: Some(&settings),
+        };
+        assert_eq!(import(&mut db, &opts).await, (2, 2, 0));
+        assert_eq!(
+            saved(&db).await,
+            vec!["ls".to_string(), "mysql --password=[REDACTED]".to_string()]
+        );
+
+        // --no-filter
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        let opts = ImportOptions {
+            dry_run: false,
+            filter: None,
+        };
+        assert_eq!(import(&mut db, &opts).await, (4, 0, 0));
+        let saved = saved(&db).await;
+        assert_eq!(saved.len(), 4);
+        assert!(saved.contains(&" echo hidden".to_string()));
+    }
+
+    #[tokio::test]
+    async fn dry_run() {
+        let settings = filter_settings();
+        let dry_run = ImportOptions {
+            dry_run: true,
+            filter: Some(&settings),
+        };
+
+        let mut db = Sqlite::new("sqlite::memory:").await.unwrap();
+        assert_eq!(import(&mut db, &dry_run).await, (2, 2, 0));
+        assert!(saved(&db).await.is_empty());
+
+        // entries repeated within the history are only counted once
+        let history = filter_history();
+        let mut loader = HistoryImporter::new(&mut db, history.len() * 2, &dry_run);
+        for h in history.iter().chain(&history) {
+            loader.push(h.clone()).await.unwrap();
+        }
+        assert_eq!(loader.flush().await.unwrap(), (2, 4, 2));
+
+        // and entries that are already saved are not counted at all
+        let opts = ImportOptions {
+            dry_run: false,
+            filter: Some(&settings),
+        };
+        import(&mut db, &opts).await;
+        assert_eq!(import(&mut db, &dry_run).await, (0, 2, 2));
+    }
+}
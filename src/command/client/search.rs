@@ -1,26 +1,31 @@
 use atuin_common::utils;
 use chrono::Utc;
-use clap::Parser;
+use clap::{Args, Parser};
 use eyre::Result;
 
 use atuin_client::{
     database::current_context,
-    database::Database,
-    history::History,
-    settings::{FilterMode, SearchMode, Settings},
+    database::{Database, SearchFilter},
+    settings::{AcceptMode, FilterMode, SearchMode, Settings},
 };
 
-use super::history::ListMode;
+use super::history::{ListMode, OutputFormat};
 
 mod cursor;
 mod duration;
 mod history_list;
 mod interactive;
+mod keybindings;
+mod theme;
 pub use duration::{format_duration, format_duration_into};
 
-#[allow(clippy::struct_excessive_bools)]
-#[derive(Parser)]
-pub struct Cmd {
+/// Written before an interactively accepted command that should be ran straight
+/// away, rather than put on the command line for editing
+const ACCEPT_RUN_PREFIX: &str = "__atuin_accept__:";
+
+/// The filters shared by everything that lists history
+#[derive(Args)]
+pub struct FilterArgs {
     /// Filter search result by directory
     #[arg(long, short)]
     cwd: Option<String>,
@@ -37,6 +42,38 @@ pub struct Cmd {
     #[arg(long = "exclude-exit")]
     exclude_exit: Option<i64>,
 
+    /// Only include results that ran for at least this long (eg: 500ms, 2m)
+    #[arg(long = "min-duration", value_parser = parse_duration_nanos)]
+    min_duration: Option<i64>,
+
+    /// Only include results that ran for at most this long (eg: 500ms, 2m)
+    #[arg(long = "max-duration", value_parser = parse_duration_nanos)]
+    max_duration: Option<i64>,
+
+    /// Filter search result by hostname
+    #[arg(long)]
+    host: Option<String>,
+
+    /// Exclude results from this hostname
+    #[arg(long = "exclude-host")]
+    exclude_host: Option<String>,
+
+    /// Filter search result by username
+    #[arg(long)]
+    user: Option<String>,
+
+    /// Exclude results from this username
+    #[arg(long = "exclude-user")]
+    exclude_user: Option<String>,
+
+    /// Filter search result by session id
+    #[arg(long)]
+    session: Option<String>,
+
+    /// Exclude results from this session id
+    #[arg(long = "exclude-session")]
+    exclude_session: Option<String>,
+
     /// Only include results added before this date
     #[arg(long, short)]
     before: Option<String>,
@@ -48,6 +85,52 @@ pub struct Cmd {
     /// How many entries to return at most
     #[arg(long)]
     limit: Option<i64>,
+}
+
+impl FilterArgs {
+    pub fn filter_options(&self) -> SearchFilter {
+        let cwd = if self.cwd.as_deref() == Some(".") {
+            Some(utils::get_current_dir())
+        } else {
+            self.cwd.clone()
+        };
+
+        let before = self.before.as_ref().and_then(|b| {
+            interim::parse_date_string(b.as_str(), Utc::now(), interim::Dialect::Uk)
+                .map_or(None, |d| Some(d.timestamp_nanos()))
+        });
+
+        let after = self.after.as_ref().and_then(|a| {
+            interim::parse_date_string(a.as_str(), Utc::now(), interim::Dialect::Uk)
+                .map_or(None, |d| Some(d.timestamp_nanos()))
+        });
+
+        SearchFilter {
+            exit: self.exit,
+            exclude_exit: self.exclude_exit,
+            cwd,
+            exclude_cwd: self.exclude_cwd.clone(),
+            session: self.session.clone(),
+            exclude_session: self.exclude_session.clone(),
+            host: self.host.clone(),
+            exclude_host: self.exclude_host.clone(),
+            user: self.user.clone(),
+            exclude_user: self.exclude_user.clone(),
+            min_duration: self.min_duration,
+            max_duration: self.max_duration,
+            before,
+            after,
+            limit: self.limit,
+            ..SearchFilter::default()
+        }
+    }
+}
+
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Parser)]
+pub struct Cmd {
+    #[command(flatten)]
+    filter: FilterArgs,
 
     /// Open interactive search UI
     #[arg(long, short)]
@@ -75,16 +158,25 @@ pub struct Cmd {
     #[arg(long)]
     cmd_only: bool,
 
-    /// Available variables: {command}, {directory}, {duration}, {user}, {host} and {time}.
+    /// Available variables: {command}, {directory}, {duration}, {user}, {host}, {time}, {time:<strftime>},
+    /// {relativetime}, {exit}, {success}, {session} and {id}.
     /// Example: --format "{time} - [{duration}] - {directory}$\t{command}"
     #[arg(long, short)]
     format: Option<String>,
+
+    /// Output machine-readable records instead of formatted text
+    #[arg(long, value_enum, conflicts_with_all = ["human", "cmd_only", "format"])]
+    output: Option<OutputFormat>,
 }
 
 impl Cmd {
     pub async fn run(self, db: &mut impl Database, settings: &mut Settings) -> Result<()> {
         if self.search_mode.is_some() {
             settings.search_mode = self.search_mode.unwrap();
+        } else if self.interactive && settings.remember_search_mode {
+            if let Some(mode) = Settings::last_search_mode() {
+                settings.search_mode = mode;
+            }
         }
         if self.filter_mode.is_some() {
             settings.filter_mode = self.filter_mode.unwrap();
@@ -93,21 +185,21 @@ impl Cmd {
         settings.shell_up_key_binding = self.shell_up_key_binding;
 
         if self.interactive {
-            let item = interactive::history(&self.query, settings, db).await?;
-            eprintln!("{item}");
+            let (accept_mode, item) = interactive::history(&self.query, settings, db).await?;
+            // see the docs for `atuin search` for the protocol the shell widgets rely on
+            if accept_mode == AcceptMode::Run && !item.is_empty() {
+                eprintln!("{ACCEPT_RUN_PREFIX}{item}");
+            } else {
+                eprintln!("{item}");
+            }
         } else {
-            let list_mode = ListMode::from_flags(self.human, self.cmd_only);
+            let list_mode = ListMode::from_flags(self.human, self.cmd_only, self.output);
+            let filter_options = self.filter.filter_options();
             let entries = run_non_interactive(
                 settings,
                 list_mode,
-                self.cwd,
-                self.exit,
-                self.exclude_exit,
-                self.exclude_cwd,
-                self.format,
-                self.before,
-                self.after,
-                self.limit,
+                &filter_options,
+                self.format.as_deref(),
                 &self.query,
                 db,
             )
@@ -120,87 +212,31 @@ impl Cmd {
     }
 }
 
-// This is supposed to more-or-less mirror the command line version, so ofc
-// it is going to have a lot of args
-#[allow(clippy::too_many_arguments)]
+fn parse_duration_nanos(s: &str) -> Result<i64, String> {
+    let d = parse_duration::parse(s).map_err(|e| e.to_string())?;
+    i64::try_from(d.as_nanos()).map_err(|_| format!("duration {s} is too long"))
+}
+
 async fn run_non_interactive(
     settings: &Settings,
     list_mode: ListMode,
-    cwd: Option<String>,
-    exit: Option<i64>,
-    exclude_exit: Option<i64>,
-    exclude_cwd: Option<String>,
-    format: Option<String>,
-    before: Option<String>,
-    after: Option<String>,
-    limit: Option<i64>,
+    filter_options: &SearchFilter,
+    format: Option<&str>,
     query: &[String],
     db: &mut impl Database,
 ) -> Result<usize> {
-    let dir = if cwd.as_deref() == Some(".") {
-        Some(utils::get_current_dir())
-    } else {
-        cwd
-    };
-
     let context = current_context();
 
-    let before = before.and_then(|b| {
-        interim::parse_date_string(b.as_str(), Utc::now(), interim::Dialect::Uk)
-            .map_or(None, |d| Some(d.timestamp_nanos()))
-    });
-
-    let after = after.and_then(|a| {
-        interim::parse_date_string(a.as_str(), Utc::now(), interim::Dialect::Uk)
-            .map_or(None, |d| Some(d.timestamp_nanos()))
-    });
-
     let results = db
         .search(
             settings.search_mode,
             settings.filter_mode,
             &context,
             query.join(" ").as_str(),
-            limit,
-            before,
-            after,
+            filter_options,
         )
         .await?;
 
-    // TODO: This filtering would be better done in the SQL query, I just
-    // need a nice way of building queries.
-    let results: Vec<History> = results
-        .iter()
-        .filter(|h| {
-            if let Some(exit) = exit {
-                if h.exit != exit {
-                    return false;
-                }
-            }
-
-            if let Some(exit) = exclude_exit {
-                if h.exit == exit {
-                    return false;
-                }
-            }
-
-            if let Some(cwd) = &exclude_cwd {
-                if h.cwd.as_str() == cwd.as_str() {
-                    return false;
-                }
-            }
-
-            if let Some(cwd) = &dir {
-                if h.cwd.as_str() != cwd.as_str() {
-                    return false;
-                }
-            }
-
-            true
-        })
-        .map(std::borrow::ToOwned::to_owned)
-        .collect();
-
-    super::history::print_list(&results, list_mode, format.as_deref());
+    super::history::print_list(&results, list_mode, format);
     Ok(results.len())
 }
@@ -92,6 +92,25 @@ impl WordJumper<'_> {
     }
 }
 
+/// Character classes used by vim word motions. A vim "word" is a run of
+/// word chars, or a run of any other non-blank chars.
+#[derive(PartialEq, Eq)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+fn char_class(c: char, word_chars: &str) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Whitespace
+    } else if word_chars.contains(c) {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
 impl Cursor {
     pub fn as_str(&self) -> &str {
         self.source.as_str()
@@ -203,6 +222,102 @@ impl Cursor {
     pub fn start(&mut self) {
         self.index = 0;
     }
+
+    /// Byte index of the start of the next vim word, as with `w`
+    fn vim_next_word_pos(&self, word_chars: &str) -> usize {
+        let mut chars = self.source[self.index..].char_indices().peekable();
+        if let Some((_, c)) = chars.next() {
+            let class = char_class(c, word_chars);
+            if class != CharClass::Whitespace {
+                while chars
+                    .next_if(|(_, c)| char_class(*c, word_chars) == class)
+                    .is_some()
+                {}
+            }
+        }
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        chars
+            .peek()
+            .map_or(self.source.len(), |(i, _)| self.index + i)
+    }
+
+    /// Byte index of the start of the previous vim word, as with `b`
+    fn vim_prev_word_pos(&self, word_chars: &str) -> usize {
+        let mut chars = self.source[..self.index].char_indices().rev().peekable();
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let (mut index, class) = match chars.next() {
+            Some((i, c)) => (i, char_class(c, word_chars)),
+            None => return 0,
+        };
+        while let Some((i, _)) = chars.next_if(|(_, c)| char_class(*c, word_chars) == class) {
+            index = i;
+        }
+        index
+    }
+
+    /// Byte index of the last char of this or the next vim word, as with `e`
+    fn vim_word_end_pos(&self, word_chars: &str) -> usize {
+        let mut chars = self.source[self.index..].char_indices().skip(1).peekable();
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let (mut index, class) = match chars.next() {
+            Some((i, c)) => (i, char_class(c, word_chars)),
+            None => return self.index,
+        };
+        while let Some((i, _)) = chars.next_if(|(_, c)| char_class(*c, word_chars) == class) {
+            index = i;
+        }
+        self.index + index
+    }
+
+    pub fn vim_next_word(&mut self, word_chars: &str) {
+        self.index = self.vim_next_word_pos(word_chars);
+    }
+
+    pub fn vim_prev_word(&mut self, word_chars: &str) {
+        self.index = self.vim_prev_word_pos(word_chars);
+    }
+
+    pub fn vim_word_end(&mut self, word_chars: &str) {
+        self.index = self.vim_word_end_pos(word_chars);
+    }
+
+    /// Delete up to the start of the next word, as with `dw`
+    pub fn vim_delete_word(&mut self, word_chars: &str) {
+        let end = self.vim_next_word_pos(word_chars);
+        self.source.replace_range(self.index..end, "");
+    }
+
+    /// Delete the rest of the word under the cursor, as with `cw`. Unlike
+    /// `dw` this keeps the whitespace after the word.
+    pub fn vim_change_word(&mut self, word_chars: &str) {
+        let class = match self.char() {
+            Some(c) if !c.is_whitespace() => char_class(c, word_chars),
+            _ => return self.vim_delete_word(word_chars),
+        };
+        let end = self.source[self.index..]
+            .char_indices()
+            .find(|(_, c)| char_class(*c, word_chars) != class)
+            .map_or(self.source.len(), |(i, _)| self.index + i);
+        self.source.replace_range(self.index..end, "");
+    }
+
+    /// Move to the last char, as with `$`
+    pub fn vim_end(&mut self) {
+        self.end();
+        self.left();
+    }
+
+    /// Delete the char under the cursor, as with `x`. Past the end of the line
+    /// that is the last char, and the cursor stays on a char afterwards
+    pub fn vim_remove(&mut self) {
+        if self.char().is_none() {
+            self.vim_end();
+        }
+        self.remove();
+        if self.char().is_none() {
+            self.left();
+        }
+    }
 }
 
 #[cfg(test)]
@@ -330,4 +445,57 @@ mod cursor_tests {
         assert_eq!(c.substring(), "öaöbögöh");
         assert_eq!(c.as_str(), "öaöbögöhöcödöeöfö");
     }
+
+    #[test]
+    fn vim_words() {
+        let word_chars = EMACS_WORD_JUMPER.word_chars;
+        // ö isn't a word char, so "ö'" is a single word
+        let mut c = Cursor::from(String::from("git commit -m 'fix ö'"));
+        c.start();
+        let indices = [4, 11, 12, 14, 15, 19, 22, 22];
+        for i in indices {
+            c.vim_next_word(word_chars);
+            assert_eq!(c.index, i);
+        }
+        let indices = [19, 15, 14, 12, 11, 4, 0, 0];
+        for i in indices {
+            c.vim_prev_word(word_chars);
+            assert_eq!(c.index, i);
+        }
+        let indices = [2, 9, 11, 12, 14, 17, 21, 21];
+        for i in indices {
+            c.vim_word_end(word_chars);
+            assert_eq!(c.index, i);
+        }
+    }
+
+    #[test]
+    fn vim_delete_change() {
+        let word_chars = EMACS_WORD_JUMPER.word_chars;
+        let mut c = Cursor::from(String::from("git commit -m msg"));
+        c.start();
+        c.vim_next_word(word_chars);
+        c.vim_delete_word(word_chars);
+        assert_eq!(c.as_str(), "git -m msg");
+        c.vim_change_word(word_chars);
+        assert_eq!(c.as_str(), "git m msg");
+        c.vim_end();
+        assert_eq!(c.char(), Some('g'));
+        c.vim_change_word(word_chars);
+        assert_eq!(c.as_str(), "git m ms");
+    }
+
+    #[test]
+    fn vim_remove() {
+        let mut c = Cursor::from(String::from("lsö"));
+        c.vim_end();
+        c.vim_remove();
+        assert_eq!((c.as_str(), c.char()), ("ls", Some('s')));
+        c.end();
+        c.vim_remove();
+        assert_eq!((c.as_str(), c.char()), ("l", Some('l')));
+        c.vim_remove();
+        c.vim_remove();
+        assert_eq!((c.as_str(), c.char()), ("", None));
+    }
 }
@@ -3,16 +3,22 @@ use std::time::Duration;
 use crate::tui::{
     buffer::Buffer,
     layout::Rect,
-    style::{Color, Modifier, Style},
+    style::Style,
     widgets::{Block, StatefulWidget, Widget},
 };
-use atuin_client::history::History;
+use atuin_client::{history::History, matching::match_indices, settings::SearchMode};
 
-use super::format_duration;
+use super::{format_duration, theme::Theme};
 
 pub struct HistoryList<'a> {
     history: &'a [History],
     block: Option<Block<'a>>,
+    // the query to highlight matches of
+    query: &'a str,
+    search_mode: SearchMode,
+    theme: &'a Theme,
+    // entries marked to be accepted together
+    marked: &'a [History],
 }
 
 #[derive(Default)]
@@ -61,13 +67,17 @@ impl<'a> StatefulWidget for HistoryList<'a> {
             x: 0,
             y: 0,
             state,
+            theme: self.theme,
         };
 
         for item in self.history.iter().skip(state.offset).take(end - start) {
-            s.index();
+            s.index(self.marked.iter().any(|m| m.id == item.id));
             s.duration(item);
             s.time(item);
-            s.command(item);
+            s.command(
+                item,
+                &match_indices(self.search_mode, self.query, &item.command),
+            );
 
             // reset line
             s.y += 1;
@@ -77,13 +87,28 @@ impl<'a> StatefulWidget for HistoryList<'a> {
 }
 
 impl<'a> HistoryList<'a> {
-    pub fn new(history: &'a [History]) -> Self {
+    pub fn new(history: &'a [History], theme: &'a Theme) -> Self {
         Self {
             history,
             block: None,
+            query: "",
+            search_mode: SearchMode::Prefix,
+            theme,
+            marked: &[],
         }
     }
 
+    pub fn marked(mut self, marked: &'a [History]) -> Self {
+        self.marked = marked;
+        self
+    }
+
+    pub fn highlight(mut self, search_mode: SearchMode, query: &'a str) -> Self {
+        self.search_mode = search_mode;
+        self.query = query;
+        self
+    }
+
     pub fn block(mut self, block: Block<'a>) -> Self {
         self.block = Some(block);
         self
@@ -110,6 +135,7 @@ struct DrawState<'a> {
     x: u16,
     y: u16,
     state: &'a ListState,
+    theme: &'a Theme,
 }
 
 // longest line prefix I could come up with
@@ -117,7 +143,7 @@ struct DrawState<'a> {
 pub const PREFIX_LENGTH: u16 = " > 123ms 59s ago".len() as u16;
 
 impl DrawState<'_> {
-    fn index(&mut self) {
+    fn index(&mut self, marked: bool) {
         // these encode the slices of `" > "`, `" {n} "`, or `"   "` in a compact form.
         // Yes, this is a hack, but it makes me feel happy
         static SLICES: &str = " > 1 2 3 4 5 6 7 8 9   ";
@@ -125,22 +151,29 @@ impl DrawState<'_> {
         let i = self.y as usize + self.state.offset;
         let i = i.checked_sub(self.state.selected);
         let i = i.unwrap_or(10).min(10) * 2;
-        self.draw(&SLICES[i..i + 3], Style::default());
+
+        // marked entries replace the leading space with a star
+        if marked {
+            self.draw("*", self.theme.selected);
+            self.draw(&SLICES[i + 1..i + 3], Style::default());
+        } else {
+            self.draw(&SLICES[i..i + 3], Style::default());
+        }
     }
 
     fn duration(&mut self, h: &History) {
-        let status = Style::default().fg(if h.success() {
-            Color::Green
+        let status = if h.success() {
+            self.theme.success
         } else {
-            Color::Red
-        });
+            self.theme.failure
+        };
         let duration = Duration::from_nanos(u64::try_from(h.duration).unwrap_or(0));
         self.draw(&format_duration(duration), status);
     }
 
     #[allow(clippy::cast_possible_truncation)] // we know that time.len() will be <6
     fn time(&mut self, h: &History) {
-        let style = Style::default().fg(Color::Blue);
+        let style = self.theme.time;
 
         // Account for the chance that h.timestamp is "in the future"
         // This would mean that "since" is negative, and the unwrap here
@@ -157,20 +190,38 @@ impl DrawState<'_> {
         self.draw(" ago", style);
     }
 
-    fn command(&mut self, h: &History) {
-        let mut style = Style::default();
-        if self.y as usize + self.state.offset == self.state.selected {
-            style = style.fg(Color::Red).add_modifier(Modifier::BOLD);
-        }
+    /// `matches` are the (sorted) indices of the chars matching the query
+    fn command(&mut self, h: &History, matches: &[usize]) {
+        let style = if self.y as usize + self.state.offset == self.state.selected {
+            self.theme.selected
+        } else {
+            Style::default()
+        };
+
+        // each whitespace separated section is drawn after a single space
+        let mut in_section = false;
+        for (i, c) in h.command.chars().enumerate() {
+            if c.is_ascii_whitespace() {
+                in_section = false;
+                continue;
+            }
 
-        for section in h.command.split_ascii_whitespace() {
-            self.x += 1;
-            if self.x > self.list_area.width {
-                // Avoid attempting to draw a command section beyond the width
-                // of the list
-                return;
+            if !in_section {
+                in_section = true;
+                self.x += 1;
+                if self.x > self.list_area.width {
+                    // Avoid attempting to draw a command section beyond the width
+                    // of the list
+                    return;
+                }
             }
-            self.draw(section, style);
+
+            let style = if matches.binary_search(&i).is_ok() {
+                style.patch(self.theme.highlight)
+            } else {
+                style
+            };
+            self.draw(c.encode_utf8(&mut [0; 4]), style);
         }
     }
 
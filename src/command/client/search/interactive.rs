@@ -5,13 +5,13 @@ use std::{
 
 use crate::tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     text::{Span, Spans, Text},
     widgets::{Block, BorderType, Borders, Paragraph},
-    Frame, Terminal,
+    Frame, Terminal, TerminalOptions, Viewport,
 };
 use crossterm::{
+    cursor,
     event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent},
     execute, terminal,
 };
@@ -23,48 +23,86 @@ use unicode_width::UnicodeWidthStr;
 use atuin_client::{
     database::current_context,
     database::Context,
-    database::Database,
+    database::{CommandStats, Database, SearchFilter},
     history::History,
-    settings::{ExitMode, FilterMode, SearchMode, Settings},
+    settings::{AcceptMode, ExitMode, FilterMode, KeyAction, KeymapMode, SearchMode, Settings},
 };
 
 use super::{
     cursor::Cursor,
-    history_list::{HistoryList, ListState, PREFIX_LENGTH},
+    format_duration,
+    history_list::{HistoryList, ListState},
+    keybindings::KeyBindings,
+    theme::Theme,
 };
 use crate::VERSION;
 
 const RETURN_ORIGINAL: usize = usize::MAX;
 const RETURN_QUERY: usize = usize::MAX - 1;
 
+// number of lines of metadata shown below the command in the preview
+const DETAILS_HEIGHT: u16 = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DeleteScope {
+    /// Only the selected entry
+    Entry,
+    /// Every entry with the same command as the selected one
+    Command,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VimMode {
+    Insert,
+    Normal,
+}
+
 struct State {
     history_count: i64,
     input: Cursor,
     filter_mode: FilterMode,
+    search_mode: SearchMode,
     results_state: ListState,
     context: Context,
     update_needed: Option<Version>,
+    show_details: bool,
+    // stats for the selected command, and the command they belong to
+    command_stats: Option<(String, CommandStats)>,
+    // waiting for the user to confirm deleting the selected entry
+    confirm_delete: bool,
+    // confirmed, to be deleted before the next draw
+    delete: Option<DeleteScope>,
+    // copy the selected command to the clipboard before the next draw
+    copy: bool,
+    // what to do with the accepted command, from `accept_mode` unless a key
+    // asks for something else
+    accept_mode: AcceptMode,
+    // entries to accept together, instead of the selected one
+    marked: Vec<History>,
+    keys: KeyBindings,
+    theme: Theme,
+    // only set when using the vim keymap
+    vim_mode: Option<VimMode>,
+    // an operator such as `d` waiting for its motion
+    vim_pending: Option<char>,
 }
 
 impl State {
-    async fn query_results(
-        &mut self,
-        search_mode: SearchMode,
-        db: &mut impl Database,
-    ) -> Result<Vec<History>> {
+    async fn query_results(&mut self, db: &mut impl Database) -> Result<Vec<History>> {
         let i = self.input.as_str();
         let results = if i.is_empty() {
             db.list(self.filter_mode, &self.context, Some(200), true)
                 .await?
         } else {
             db.search(
-                search_mode,
+                self.search_mode,
                 self.filter_mode,
                 &self.context,
                 i,
-                Some(200),
-                None,
-                None,
+                &SearchFilter {
+                    limit: Some(200),
+                    ..SearchFilter::default()
+                },
             )
             .await?
         };
@@ -73,10 +111,101 @@ impl State {
         Ok(results)
     }
 
-    fn handle_input(&mut self, settings: &Settings, input: &Event, len: usize) -> Option<usize> {
+    async fn delete_selected(
+        &mut self,
+        scope: DeleteScope,
+        results: &[History],
+        db: &mut impl Database,
+    ) -> Result<()> {
+        let Some(selected) = results.get(self.results_state.selected()) else {
+            return Ok(());
+        };
+
+        match scope {
+            DeleteScope::Entry => {
+                db.delete(selected).await?;
+                self.marked.retain(|m| m.id != selected.id);
+            }
+            DeleteScope::Command => {
+                let duplicates = db
+                    .query_history(&SearchFilter {
+                        command: Some(selected.command.clone()),
+                        ..SearchFilter::default()
+                    })
+                    .await?;
+                db.delete_bulk(&duplicates).await?;
+                self.marked.retain(|m| m.command != selected.command);
+            }
+        }
+
+        self.history_count = db.history_count().await?;
+        self.command_stats = None;
+
+        Ok(())
+    }
+
+    async fn update_command_stats(
+        &mut self,
+        results: &[History],
+        db: &impl Database,
+    ) -> Result<()> {
+        if !self.show_details {
+            return Ok(());
+        }
+
+        let Some(selected) = results.get(self.results_state.selected()) else {
+            return Ok(());
+        };
+
+        if matches!(&self.command_stats, Some((command, _)) if *command == selected.command) {
+            return Ok(());
+        }
+
+        let stats = db.command_stats(&selected.command).await?;
+        self.command_stats = Some((selected.command.clone(), stats));
+
+        Ok(())
+    }
+
+    /// The command to return for the `index` the search finished with, and
+    /// whether it should be ran straight away
+    fn into_accepted(
+        mut self,
+        index: usize,
+        mut results: Vec<History>,
+        settings: &Settings,
+    ) -> (AcceptMode, String) {
+        if index == RETURN_ORIGINAL {
+            (AcceptMode::Edit, String::new())
+        } else if index == RETURN_QUERY {
+            (AcceptMode::Edit, self.input.into_inner())
+        } else if !self.marked.is_empty() {
+            (self.accept_mode, self.marked_commands(settings))
+        } else if index < results.len() {
+            // index is in bounds so we return that entry
+            (self.accept_mode, results.swap_remove(index).command)
+        } else {
+            // out of bounds -> usually implies no selected entry so we return the input
+            (AcceptMode::Edit, self.input.into_inner())
+        }
+    }
+
+    /// The marked commands in the order they were ran, so they can be replayed
+    fn marked_commands(&mut self, settings: &Settings) -> String {
+        self.marked.sort_by_key(|h| h.timestamp);
+        let commands: Vec<_> = self.marked.iter().map(|h| h.command.as_str()).collect();
+        commands.join(settings.multi_select_join.separator())
+    }
+
+    fn handle_input(
+        &mut self,
+        settings: &Settings,
+        input: &Event,
+        results: &[History],
+    ) -> Option<usize> {
         match input {
-            Event::Key(k) => self.handle_key_input(settings, k, len),
-            Event::Mouse(m) => self.handle_mouse_input(*m, len),
+            Event::Key(k) => self.handle_key_input(settings, k, results),
+            Event::Mouse(m) => self.handle_mouse_input(*m, results.len()),
             _ => None,
         }
     }
@@ -96,65 +225,172 @@ impl State {
         None
     }
 
-    #[allow(clippy::too_many_lines)]
     fn handle_key_input(
         &mut self,
         settings: &Settings,
         input: &KeyEvent,
-        len: usize,
+        results: &[History],
     ) -> Option<usize> {
         if input.kind == event::KeyEventKind::Release {
             return None;
         }
 
-        let ctrl = input.modifiers.contains(KeyModifiers::CONTROL);
-        let alt = input.modifiers.contains(KeyModifiers::ALT);
-        match input.code {
-            KeyCode::Char('c' | 'd' | 'g') if ctrl => return Some(RETURN_ORIGINAL),
-            KeyCode::Esc => {
+        // any key other than a confirmation cancels the delete
+        if self.confirm_delete {
+            self.confirm_delete = false;
+            self.delete = match input.code {
+                KeyCode::Char('y') => Some(DeleteScope::Entry),
+                KeyCode::Char('a') => Some(DeleteScope::Command),
+                _ => None,
+            };
+            return None;
+        }
+
+        if let Some(handled) = self.handle_vim_key_input(settings, input, results) {
+            return handled;
+        }
+
+        if let Some(action) = self.keys.get(input) {
+            return self.handle_action(settings, action, results);
+        }
+
+        if let KeyCode::Char(c) = input.code {
+            self.input.insert(c);
+        }
+
+        None
+    }
+
+    /// Returns `None` for keys that aren't handled by the vim keymap, which
+    /// then fall through to the usual key bindings
+    #[allow(clippy::option_option)]
+    fn handle_vim_key_input(
+        &mut self,
+        settings: &Settings,
+        input: &KeyEvent,
+        results: &[History],
+    ) -> Option<Option<usize>> {
+        // bindings with ctrl or alt work the same in every mode
+        if !(input.modifiers - KeyModifiers::SHIFT).is_empty() {
+            return None;
+        }
+
+        match self.vim_mode? {
+            VimMode::Insert if input.code == KeyCode::Esc => {
+                self.vim_mode = Some(VimMode::Normal);
+                self.input.left();
+                return Some(None);
+            }
+            VimMode::Insert => return None,
+            VimMode::Normal => {}
+        }
+
+        let word_chars = settings.word_chars.as_str();
+        match (self.vim_pending.take(), input.code) {
+            (Some('d'), KeyCode::Char('w')) => self.input.vim_delete_word(word_chars),
+            (Some('c'), KeyCode::Char('w')) => {
+                self.input.vim_change_word(word_chars);
+                self.vim_mode = Some(VimMode::Insert);
+            }
+            (None, KeyCode::Char(c @ ('d' | 'c'))) => self.vim_pending = Some(c),
+            (None, KeyCode::Esc) => {
+                return Some(self.handle_action(settings, KeyAction::Exit, results))
+            }
+            (None, KeyCode::Char('h')) => {
+                self.input.left();
+            }
+            (None, KeyCode::Char('l')) => {
+                self.input.right();
+                // normal mode stays on the last char rather than past it
+                if self.input.char().is_none() {
+                    self.input.left();
+                }
+            }
+            (None, KeyCode::Char('w')) => self.input.vim_next_word(word_chars),
+            (None, KeyCode::Char('b')) => self.input.vim_prev_word(word_chars),
+            (None, KeyCode::Char('e')) => self.input.vim_word_end(word_chars),
+            (None, KeyCode::Char('0')) => self.input.start(),
+            (None, KeyCode::Char('$')) => self.input.vim_end(),
+            (None, KeyCode::Char('x')) => self.input.vim_remove(),
+            (None, KeyCode::Char('j')) => {
+                return Some(self.handle_action(settings, KeyAction::MoveDown, results))
+            }
+            (None, KeyCode::Char('k')) => {
+                return Some(self.handle_action(settings, KeyAction::MoveUp, results))
+            }
+            (None, KeyCode::Char('/' | 'i')) => self.vim_mode = Some(VimMode::Insert),
+            (None, KeyCode::Char('a')) => {
+                self.input.right();
+                self.vim_mode = Some(VimMode::Insert);
+            }
+            (None, KeyCode::Char('I')) => {
+                self.input.start();
+                self.vim_mode = Some(VimMode::Insert);
+            }
+            (None, KeyCode::Char('A')) => {
+                self.input.end();
+                self.vim_mode = Some(VimMode::Insert);
+            }
+            // an unknown motion cancels the operator, and no other key in
+            // normal mode may end up in the query
+            (Some(_), _) | (None, KeyCode::Char(_)) => {}
+            (None, _) => return None,
+        }
+
+        Some(None)
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn handle_action(
+        &mut self,
+        settings: &Settings,
+        action: KeyAction,
+        results: &[History],
+    ) -> Option<usize> {
+        let len = results.len();
+        match action {
+            KeyAction::ReturnOriginal => return Some(RETURN_ORIGINAL),
+            KeyAction::ReturnQuery => return Some(RETURN_QUERY),
+            KeyAction::Exit => {
                 return Some(match settings.exit_mode {
                     ExitMode::ReturnOriginal => RETURN_ORIGINAL,
                     ExitMode::ReturnQuery => RETURN_QUERY,
                 })
             }
-            KeyCode::Enter => {
+            KeyAction::Accept => return Some(self.results_state.selected()),
+            KeyAction::AcceptRun | KeyAction::AcceptEdit => {
+                self.accept_mode = match action {
+                    KeyAction::AcceptRun => AcceptMode::Run,
+                    _ => AcceptMode::Edit,
+                };
                 return Some(self.results_state.selected());
             }
-            KeyCode::Char(c @ '1'..='9') if alt => {
-                let c = c.to_digit(10)? as usize;
-                return Some(self.results_state.selected() + c);
+            KeyAction::Select(n) => return Some(self.results_state.selected() + usize::from(n)),
+            KeyAction::CursorLeft => {
+                self.input.left();
             }
-            KeyCode::Left if ctrl => self
+            KeyAction::CursorWordLeft => self
                 .input
                 .prev_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Left => {
-                self.input.left();
-            }
-            KeyCode::Char('h') if ctrl => {
-                self.input.left();
-            }
-            KeyCode::Right if ctrl => self
+            KeyAction::CursorRight => self.input.right(),
+            KeyAction::CursorWordRight => self
                 .input
                 .next_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Right => self.input.right(),
-            KeyCode::Char('l') if ctrl => self.input.right(),
-            KeyCode::Char('a') if ctrl => self.input.start(),
-            KeyCode::Home => self.input.start(),
-            KeyCode::Char('e') if ctrl => self.input.end(),
-            KeyCode::End => self.input.end(),
-            KeyCode::Backspace if ctrl => self
-                .input
-                .remove_prev_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Backspace => {
+            KeyAction::CursorStart => self.input.start(),
+            KeyAction::CursorEnd => self.input.end(),
+            KeyAction::DeleteCharBefore => {
                 self.input.back();
             }
-            KeyCode::Delete if ctrl => self
+            KeyAction::DeleteWordBefore => self
                 .input
-                .remove_next_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Delete => {
+                .remove_prev_word(&settings.word_chars, settings.word_jump_mode),
+            KeyAction::DeleteCharAfter => {
                 self.input.remove();
             }
-            KeyCode::Char('w') if ctrl => {
+            KeyAction::DeleteWordAfter => self
+                .input
+                .remove_next_word(&settings.word_chars, settings.word_jump_mode),
+            KeyAction::DeleteToWhitespace => {
                 // remove the first batch of whitespace
                 while matches!(self.input.back(), Some(c) if c.is_whitespace()) {}
                 while self.input.left() {
@@ -165,47 +401,63 @@ impl State {
                     self.input.remove();
                 }
             }
-            KeyCode::Char('u') if ctrl => self.input.clear(),
-            KeyCode::Char('r') if ctrl => {
-                pub static FILTER_MODES: [FilterMode; 4] = [
+            KeyAction::ClearInput => self.input.clear(),
+            KeyAction::ToggleDetails => self.show_details = !self.show_details,
+            KeyAction::DeleteEntry => self.confirm_delete = len > 0,
+            KeyAction::Copy => self.copy = len > 0,
+            KeyAction::ToggleMark => {
+                if let Some(h) = results.get(self.results_state.selected()) {
+                    if let Some(i) = self.marked.iter().position(|m| m.id == h.id) {
+                        self.marked.remove(i);
+                    } else {
+                        self.marked.push(h.clone());
+                    }
+                }
+
+                // like fzf, move on to the next entry to make marking a run easy
+                let i = self.results_state.selected() + 1;
+                self.results_state.select(i.min(len.saturating_sub(1)));
+            }
+            KeyAction::CycleFilterMode => {
+                pub static FILTER_MODES: [FilterMode; 6] = [
                     FilterMode::Global,
                     FilterMode::Host,
                     FilterMode::Session,
                     FilterMode::Directory,
+                    FilterMode::Subtree,
+                    FilterMode::Workspace,
                 ];
                 let i = self.filter_mode as usize;
                 let i = (i + 1) % FILTER_MODES.len();
                 self.filter_mode = FILTER_MODES[i];
+
+                // workspace mode only makes sense inside of a git repo
+                if self.filter_mode == FilterMode::Workspace && !self.context.in_workspace() {
+                    self.filter_mode = FilterMode::Global;
+                }
             }
-            KeyCode::Down if self.results_state.selected() == 0 => return Some(RETURN_ORIGINAL),
-            KeyCode::Down => {
-                let i = self.results_state.selected().saturating_sub(1);
-                self.results_state.select(i);
+            KeyAction::CycleSearchMode => self.search_mode = self.search_mode.next(),
+            KeyAction::MoveDownOrExit if self.results_state.selected() == 0 => {
+                return Some(RETURN_ORIGINAL)
             }
-            KeyCode::Char('n' | 'j') if ctrl => {
+            KeyAction::MoveDown | KeyAction::MoveDownOrExit => {
                 let i = self.results_state.selected().saturating_sub(1);
                 self.results_state.select(i);
             }
-            KeyCode::Up => {
+            KeyAction::MoveUp => {
                 let i = self.results_state.selected() + 1;
-                self.results_state.select(i.min(len - 1));
+                self.results_state.select(i.min(len.saturating_sub(1)));
             }
-            KeyCode::Char('p' | 'k') if ctrl => {
-                let i = self.results_state.selected() + 1;
-                self.results_state.select(i.min(len - 1));
-            }
-            KeyCode::Char(c) => self.input.insert(c),
-            KeyCode::PageDown => {
+            KeyAction::PageDown => {
                 let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
                 let i = self.results_state.selected().saturating_sub(scroll_len);
                 self.results_state.select(i);
             }
-            KeyCode::PageUp => {
+            KeyAction::PageUp => {
                 let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
                 let i = self.results_state.selected() + scroll_len;
-                self.results_state.select(i.min(len - 1));
+                self.results_state.select(i.min(len.saturating_sub(1)));
             }
-            _ => {}
         };
 
         None
@@ -222,17 +474,19 @@ impl State {
     ) {
         let border_size = if compact { 0 } else { 1 };
         let preview_width = f.size().width - 2;
-        let preview_height = if show_preview {
+        let preview_height = if show_preview || self.show_details {
             let longest_command = results
                 .iter()
                 .max_by(|h1, h2| h1.command.len().cmp(&h2.command.len()));
+            let details_height = if self.show_details { DETAILS_HEIGHT } else { 0 };
             longest_command.map_or(0, |v| {
                 std::cmp::min(
                     4,
                     (v.command.len() as u16 + preview_width - 1 - border_size)
                         / (preview_width - border_size),
                 )
-            }) + border_size * 2
+            }) + details_height
+                + border_size * 2
         } else if compact {
             0
         } else {
@@ -275,7 +529,9 @@ impl State {
         let stats = self.build_stats();
         f.render_widget(stats, header_chunks[2]);
 
-        let results_list = Self::build_results_list(compact, results);
+        let results_list = Self::build_results_list(compact, results, &self.theme)
+            .highlight(self.search_mode, self.input.as_str())
+            .marked(&self.marked);
         f.render_stateful_widget(results_list, chunks[1], &mut self.results_state);
 
         let input = self.build_input(compact, chunks[2].width.into());
@@ -284,12 +540,13 @@ impl State {
         let preview = self.build_preview(results, compact, preview_width, chunks[3].width.into());
         f.render_widget(preview, chunks[3]);
 
-        let extra_width = UnicodeWidthStr::width(self.input.substring());
+        let extra_width = UnicodeWidthStr::width(self.input_prefix().as_str())
+            + UnicodeWidthStr::width(self.input.substring());
 
         let cursor_offset = if compact { 0 } else { 1 };
         f.set_cursor(
             // Put cursor past the end of the input text
-            chunks[2].x + extra_width as u16 + PREFIX_LENGTH + 1 + cursor_offset,
+            chunks[2].x + extra_width as u16 + cursor_offset,
             chunks[2].y + cursor_offset,
         );
     }
@@ -300,43 +557,56 @@ impl State {
 
             Paragraph::new(Text::from(Span::styled(
                 format!(" Atuin v{VERSION} - UPDATE AVAILABLE {version}"),
-                Style::default().add_modifier(Modifier::BOLD).fg(Color::Red),
+                self.theme.alert,
             )))
         } else {
             Paragraph::new(Text::from(Span::styled(
                 format!(" Atuin v{VERSION}"),
-                Style::default().add_modifier(Modifier::BOLD),
+                self.theme.title,
             )))
         };
         title
     }
 
-    #[allow(clippy::unused_self)]
     fn build_help(&mut self) -> Paragraph {
+        let action = if self.vim_mode == Some(VimMode::Insert) {
+            " for normal mode"
+        } else {
+            " to exit"
+        };
         let help = Paragraph::new(Text::from(Spans::from(vec![
-            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" to exit"),
+            Span::styled("Esc", self.theme.label),
+            Span::raw(action),
         ])))
-        .style(Style::default().fg(Color::DarkGray))
+        .style(self.theme.muted)
         .alignment(Alignment::Center);
         help
     }
 
     fn build_stats(&mut self) -> Paragraph {
+        let marked = if self.marked.is_empty() {
+            String::new()
+        } else {
+            format!("{} marked, ", self.marked.len())
+        };
         let stats = Paragraph::new(Text::from(Span::raw(format!(
-            "history count: {}",
+            "{marked}history count: {}",
             self.history_count,
         ))))
-        .style(Style::default().fg(Color::DarkGray))
+        .style(self.theme.muted)
         .alignment(Alignment::Right);
         stats
     }
 
-    fn build_results_list(compact: bool, results: &[History]) -> HistoryList {
+    fn build_results_list<'a>(
+        compact: bool,
+        results: &'a [History],
+        theme: &'a Theme,
+    ) -> HistoryList<'a> {
         let results_list = if compact {
-            HistoryList::new(results)
+            HistoryList::new(results, theme)
         } else {
-            HistoryList::new(results).block(
+            HistoryList::new(results, theme).block(
                 Block::default()
                     .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
                     .border_type(BorderType::Rounded),
@@ -345,12 +615,43 @@ impl State {
         results_list
     }
 
-    fn build_input(&mut self, compact: bool, chunk_width: usize) -> Paragraph {
-        let input = format!(
-            "[{:^14}] {}",
+    /// The filter and search modes, shown before the query
+    fn input_prefix(&self) -> String {
+        format!(
+            "[{:^11}|{:^10}] ",
             self.filter_mode.as_str(),
-            self.input.as_str(),
-        );
+            self.search_mode.as_str()
+        )
+    }
+
+    fn build_input(&mut self, compact: bool, chunk_width: usize) -> Paragraph {
+        let input = if self.confirm_delete {
+            Spans::from(format!(
+                "[{:^22}] delete this entry? (y)es, (a)ll with this command, (n)o",
+                "DELETE"
+            ))
+        } else {
+            let query = format!("{}{}", self.input_prefix(), self.input.as_str());
+            let mode = match self.vim_mode {
+                Some(VimMode::Insert) => "-- INSERT --",
+                Some(VimMode::Normal) => "-- NORMAL --",
+                None => "",
+            };
+
+            // show the vim mode on the right, as long as it fits
+            let border_width = if compact { 0 } else { 2 };
+            let padding = chunk_width
+                .saturating_sub(border_width + UnicodeWidthStr::width(query.as_str()))
+                .checked_sub(mode.len());
+            match padding {
+                Some(padding) if !mode.is_empty() => Spans::from(vec![
+                    Span::raw(query),
+                    Span::raw(" ".repeat(padding)),
+                    Span::styled(mode, self.theme.muted),
+                ]),
+                _ => Spans::from(query),
+            }
+        };
         let input = if compact {
             Paragraph::new(input)
         } else {
@@ -385,10 +686,16 @@ impl State {
                 .map(|(a, b)| &s[a..b])
                 .join("\n")
         };
+        let mut text = Text::from(command);
+        if self.show_details {
+            if let Some(h) = results.get(selected) {
+                text.extend(self.build_details(h));
+            }
+        }
         let preview = if compact {
-            Paragraph::new(command).style(Style::default().fg(Color::DarkGray))
+            Paragraph::new(text).style(self.theme.muted)
         } else {
-            Paragraph::new(command).block(
+            Paragraph::new(text).block(
                 Block::default()
                     .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
                     .border_type(BorderType::Rounded)
@@ -397,33 +704,133 @@ impl State {
         };
         preview
     }
+
+    fn build_details(&self, h: &History) -> Text<'static> {
+        let label = self.theme.label;
+        let field = |name: &'static str, value: &dyn std::fmt::Display| {
+            [
+                Span::styled(format!("{name}: "), label),
+                Span::raw(format!("{value}   ")),
+            ]
+        };
+
+        let (host, user) = h.hostname.split_once(':').unwrap_or((&h.hostname, ""));
+        let duration = Duration::from_nanos(u64::try_from(h.duration).unwrap_or(0));
+        let time = h.timestamp.with_timezone(&chrono::Local);
+
+        let stats = match &self.command_stats {
+            Some((command, stats)) if *command == h.command && stats.total > 0 => {
+                #[allow(clippy::cast_precision_loss)]
+                let rate = stats.success as f64 / stats.total as f64 * 100.0;
+                format!("{} times, {rate:.0}% successful", stats.total)
+            }
+            _ => String::from("..."),
+        };
+
+        Text::from(vec![
+            Spans::from(field("directory", &h.cwd).to_vec()),
+            Spans::from(
+                [
+                    field("exit", &h.exit),
+                    field("duration", &format_duration(duration)),
+                    field("time", &time.format("%Y-%m-%d %H:%M:%S %Z")),
+                ]
+                .concat(),
+            ),
+            Spans::from(
+                [
+                    field("host", &host),
+                    field("user", &user),
+                    field("session", &h.session),
+                ]
+                .concat(),
+            ),
+            Spans::from(field("ran", &stats).to_vec()),
+        ])
+    }
+}
+
+/// Copy using the OSC 52 escape sequence, which is understood by most terminals,
+/// and passed through by multiplexers such as tmux, even over ssh
+fn copy_to_clipboard(w: &mut impl Write, text: &str) -> std::io::Result<()> {
+    write!(w, "\x1b]52;c;{}\x07", base64::encode(text))?;
+    w.flush()
 }
 
 struct Stdout {
     stdout: std::io::Stdout,
+    // the lines reserved below the prompt, and where the cursor was before,
+    // when not using the alternate screen
+    inline: Option<(Rect, (u16, u16))>,
 }
 
 impl Stdout {
-    pub fn new() -> std::io::Result<Self> {
+    /// Take over the whole screen, or with a non-zero `inline_height` just
+    /// that many lines below the prompt
+    pub fn new(inline_height: u16) -> std::io::Result<Self> {
         terminal::enable_raw_mode()?;
         let mut stdout = stdout();
-        execute!(
-            stdout,
-            terminal::EnterAlternateScreen,
-            event::EnableMouseCapture
-        )?;
-        Ok(Self { stdout })
+
+        match Self::setup(&mut stdout, inline_height) {
+            Ok(inline) => Ok(Self { stdout, inline }),
+            Err(e) => {
+                // nothing will be dropped to put the terminal back
+                let _ = terminal::disable_raw_mode();
+                Err(e)
+            }
+        }
+    }
+
+    fn setup(
+        stdout: &mut std::io::Stdout,
+        inline_height: u16,
+    ) -> std::io::Result<Option<(Rect, (u16, u16))>> {
+        let (width, rows) = terminal::size()?;
+        // the prompt needs a line too, so a terminal too small for that gets the whole screen
+        let height = inline_height.min(rows.saturating_sub(1));
+
+        let inline = if height == 0 {
+            execute!(stdout, terminal::EnterAlternateScreen)?;
+            None
+        } else {
+            let (column, row) = cursor::position()?;
+
+            // scroll the prompt up if there aren't enough lines below it
+            let overflow = (row + 1 + height).saturating_sub(rows);
+            execute!(stdout, cursor::MoveTo(0, rows - 1))?;
+            for _ in 0..overflow {
+                stdout.write_all(b"\n")?;
+            }
+
+            let area = Rect::new(0, (row + 1).saturating_sub(overflow), width, height);
+            execute!(
+                stdout,
+                cursor::MoveTo(0, area.y),
+                terminal::Clear(terminal::ClearType::FromCursorDown)
+            )?;
+            Some((area, (column, row.saturating_sub(overflow))))
+        };
+
+        execute!(stdout, event::EnableMouseCapture)?;
+        Ok(inline)
     }
 }
 
 impl Drop for Stdout {
     fn drop(&mut self) {
-        execute!(
-            self.stdout,
-            terminal::LeaveAlternateScreen,
-            event::DisableMouseCapture
-        )
-        .unwrap();
+        if let Some((area, (column, row))) = self.inline {
+            // wipe what we drew, and put the cursor back on the prompt
+            execute!(
+                self.stdout,
+                cursor::MoveTo(0, area.y),
+                terminal::Clear(terminal::ClearType::FromCursorDown),
+                cursor::MoveTo(column, row)
+            )
+            .unwrap();
+        } else {
+            execute!(self.stdout, terminal::LeaveAlternateScreen).unwrap();
+        }
+        execute!(self.stdout, event::DisableMouseCapture).unwrap();
         terminal::disable_raw_mode().unwrap();
     }
 }
@@ -438,6 +845,28 @@ impl Write for Stdout {
     }
 }
 
+/// The terminal to draw the search on, along with the area it is limited to
+/// when drawing inline
+fn create_terminal(
+    inline_height: u16,
+) -> std::io::Result<(Terminal<CrosstermBackend<Stdout>>, Option<Rect>)> {
+    let stdout = Stdout::new(inline_height)?;
+    let inline_area = stdout.inline.map(|(area, _)| area);
+    let backend = CrosstermBackend::new(stdout);
+
+    let terminal = match inline_area {
+        Some(area) => Terminal::with_options(
+            backend,
+            TerminalOptions {
+                viewport: Viewport::fixed(area),
+            },
+        ),
+        None => Terminal::new(backend)?,
+    };
+
+    Ok((terminal, inline_area))
+}
+
 // this is a big blob of horrible! clean it up!
 // for now, it works. But it'd be great if it were more easily readable, and
 // modular. I'd like to add some more stats and stuff at some point
@@ -446,10 +875,8 @@ pub async fn history(
     query: &[String],
     settings: &Settings,
     db: &mut impl Database,
-) -> Result<String> {
-    let stdout = Stdout::new()?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+) -> Result<(AcceptMode, String)> {
+    let (mut terminal, inline_area) = create_terminal(settings.inline_height)?;
 
     let mut input = Cursor::from(query.join(" "));
     // Put the cursor at the end of the query by default
@@ -470,16 +897,33 @@ pub async fn history(
         } else {
             settings.filter_mode
         },
+        search_mode: settings.search_mode,
         update_needed: None,
+        show_details: false,
+        command_stats: None,
+        confirm_delete: false,
+        delete: None,
+        copy: false,
+        accept_mode: settings.accept_mode,
+        marked: Vec::new(),
+        keys: KeyBindings::new(&settings.keys)?,
+        theme: Theme::new(&settings.theme)?,
+        vim_mode: match settings.keymap_mode {
+            KeymapMode::Emacs => None,
+            KeymapMode::Vim => Some(VimMode::Insert),
+        },
+        vim_pending: None,
     };
 
-    let mut results = app.query_results(settings.search_mode, db).await?;
+    let mut results = app.query_results(db).await?;
 
     let index = 'render: loop {
+        app.update_command_stats(&results, db).await?;
+
         let compact = match settings.style {
-            atuin_client::settings::Style::Auto => {
-                terminal.size().map(|size| size.height < 14).unwrap_or(true)
-            }
+            atuin_client::settings::Style::Auto => inline_area
+                .map_or_else(|| terminal.size(), Ok)
+                .map_or(true, |size| size.height < 14),
             atuin_client::settings::Style::Compact => true,
             atuin_client::settings::Style::Full => false,
         };
@@ -487,6 +931,7 @@ pub async fn history(
 
         let initial_input = app.input.as_str().to_owned();
         let initial_filter_mode = app.filter_mode;
+        let initial_search_mode = app.search_mode;
 
         let event_ready = tokio::task::spawn_blocking(|| event::poll(Duration::from_millis(250)));
 
@@ -494,7 +939,7 @@ pub async fn history(
             event_ready = event_ready => {
                 if event_ready?? {
                     loop {
-                        if let Some(i) = app.handle_input(settings, &event::read()?, results.len()) {
+                        if let Some(i) = app.handle_input(settings, &event::read()?, &results) {
                             break 'render i;
                         }
                         if !event::poll(Duration::ZERO)? {
@@ -508,19 +953,34 @@ pub async fn history(
             }
         }
 
-        if initial_input != app.input.as_str() || initial_filter_mode != app.filter_mode {
-            results = app.query_results(settings.search_mode, db).await?;
+        if let Some(scope) = app.delete.take() {
+            app.delete_selected(scope, &results, db).await?;
+
+            // refresh the results, but keep our place in the list
+            let selected = app.results_state.selected();
+            results = app.query_results(db).await?;
+            app.results_state
+                .select(selected.min(results.len().saturating_sub(1)));
+        } else if initial_input != app.input.as_str()
+            || initial_filter_mode != app.filter_mode
+            || initial_search_mode != app.search_mode
+        {
+            results = app.query_results(db).await?;
+        }
+
+        if std::mem::take(&mut app.copy) {
+            if let Some(h) = results.get(app.results_state.selected()) {
+                copy_to_clipboard(terminal.backend_mut(), &h.command)?;
+            }
         }
     };
-    if index < results.len() {
-        // index is in bounds so we return that entry
-        Ok(results.swap_remove(index).command)
-    } else if index == RETURN_ORIGINAL {
-        Ok(String::new())
-    } else {
-        // Either:
-        // * index == RETURN_QUERY, in which case we should return the input
-        // * out of bounds -> usually implies no selected entry so we return the input
-        Ok(app.input.into_inner())
+    if settings.remember_search_mode && app.search_mode != settings.search_mode {
+        // losing the search mode isn't worth losing the accepted command over, and
+        // stderr is where that goes, so only log it
+        if let Err(e) = Settings::save_search_mode(app.search_mode) {
+            log::warn!("failed to save the search mode: {}", e);
+        }
     }
+
+    Ok(app.into_accepted(index, results, settings))
 }
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use eyre::{bail, Result};
+
+use atuin_client::settings::KeyAction;
+
+// the bindings that are used unless overridden in the `[keys]` config
+static DEFAULT_KEYS: &[(&str, KeyAction)] = &[
+    ("ctrl-c", KeyAction::ReturnOriginal),
+    ("ctrl-d", KeyAction::ReturnOriginal),
+    ("ctrl-g", KeyAction::ReturnOriginal),
+    ("esc", KeyAction::Exit),
+    ("enter", KeyAction::Accept),
+    ("alt-enter", KeyAction::AcceptEdit),
+    ("alt-1", KeyAction::Select(1)),
+    ("alt-2", KeyAction::Select(2)),
+    ("alt-3", KeyAction::Select(3)),
+    ("alt-4", KeyAction::Select(4)),
+    ("alt-5", KeyAction::Select(5)),
+    ("alt-6", KeyAction::Select(6)),
+    ("alt-7", KeyAction::Select(7)),
+    ("alt-8", KeyAction::Select(8)),
+    ("alt-9", KeyAction::Select(9)),
+    ("left", KeyAction::CursorLeft),
+    ("ctrl-h", KeyAction::CursorLeft),
+    ("ctrl-left", KeyAction::CursorWordLeft),
+    ("right", KeyAction::CursorRight),
+    ("ctrl-l", KeyAction::CursorRight),
+    ("ctrl-right", KeyAction::CursorWordRight),
+    ("ctrl-a", KeyAction::CursorStart),
+    ("home", KeyAction::CursorStart),
+    ("ctrl-e", KeyAction::CursorEnd),
+    ("end", KeyAction::CursorEnd),
+    ("backspace", KeyAction::DeleteCharBefore),
+    ("ctrl-backspace", KeyAction::DeleteWordBefore),
+    ("delete", KeyAction::DeleteCharAfter),
+    ("ctrl-delete", KeyAction::DeleteWordAfter),
+    ("ctrl-w", KeyAction::DeleteToWhitespace),
+    ("ctrl-u", KeyAction::ClearInput),
+    ("ctrl-o", KeyAction::ToggleDetails),
+    ("ctrl-x", KeyAction::DeleteEntry),
+    ("ctrl-r", KeyAction::CycleFilterMode),
+    ("ctrl-s", KeyAction::CycleSearchMode),
+    ("tab", KeyAction::ToggleMark),
+    ("down", KeyAction::MoveDownOrExit),
+    ("ctrl-n", KeyAction::MoveDown),
+    ("ctrl-j", KeyAction::MoveDown),
+    ("up", KeyAction::MoveUp),
+    ("ctrl-p", KeyAction::MoveUp),
+    ("ctrl-k", KeyAction::MoveUp),
+    ("pagedown", KeyAction::PageDown),
+    ("pageup", KeyAction::PageUp),
+];
+
+type Chord = (KeyCode, KeyModifiers);
+
+pub struct KeyBindings(HashMap<Chord, KeyAction>);
+
+impl KeyBindings {
+    /// The default bindings, with any from the config layered on top
+    pub fn new(keys: &HashMap<String, KeyAction>) -> Result<Self> {
+        let mut bindings = HashMap::new();
+
+        let defaults = DEFAULT_KEYS.iter().map(|(k, a)| (*k, a));
+        for (chord, action) in defaults.chain(keys.iter().map(|(k, a)| (k.as_str(), a))) {
+            bindings.insert(parse_chord(chord)?, *action);
+        }
+
+        Ok(Self(bindings))
+    }
+
+    pub fn get(&self, input: &KeyEvent) -> Option<KeyAction> {
+        let mut modifiers = input.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+
+        // shift is already applied to characters, eg shift-a is sent as 'A'
+        if !matches!(input.code, KeyCode::Char(_)) {
+            modifiers |= input.modifiers & KeyModifiers::SHIFT;
+        }
+
+        self.0.get(&(input.code, modifiers)).copied()
+    }
+}
+
+/// Parse a chord such as `ctrl-r`, `alt-shift-left` or `f1`
+fn parse_chord(chord: &str) -> Result<Chord> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut key = chord;
+
+    // a bare "-" is a key, so only split when there is something after it
+    while let Some((modifier, rest)) = key.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => break,
+        };
+        key = rest;
+    }
+
+    let code = match key.to_lowercase().as_str() {
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        lower => {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => bail!("invalid key {key:?} in key binding {chord:?}"),
+                },
+            }
+        }
+    };
+
+    // same as when looking up a key event, shift is part of the character
+    let code = match code {
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+            modifiers.remove(KeyModifiers::SHIFT);
+            KeyCode::Char(c.to_ascii_uppercase())
+        }
+        code => code,
+    };
+
+    Ok((code, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use atuin_client::settings::KeyAction;
+
+    use super::{parse_chord, KeyBindings};
+
+    #[test]
+    fn parse_chords() {
+        assert_eq!(
+            parse_chord("ctrl-r").unwrap(),
+            (KeyCode::Char('r'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            parse_chord("Alt-Shift-Left").unwrap(),
+            (KeyCode::Left, KeyModifiers::ALT | KeyModifiers::SHIFT)
+        );
+        assert_eq!(
+            parse_chord("shift-a").unwrap(),
+            (KeyCode::Char('A'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            parse_chord("alt--").unwrap(),
+            (KeyCode::Char('-'), KeyModifiers::ALT)
+        );
+        assert_eq!(
+            parse_chord("f5").unwrap(),
+            (KeyCode::F(5), KeyModifiers::NONE)
+        );
+        assert!(parse_chord("ctrl-nope").is_err());
+        assert!(parse_chord("hyper-r").is_err());
+    }
+
+    #[test]
+    fn override_defaults() {
+        let keys = HashMap::from([
+            ("ctrl-r".to_string(), KeyAction::CycleSearchMode),
+            ("ctrl-y".to_string(), KeyAction::Copy),
+        ]);
+        let bindings = KeyBindings::new(&keys).unwrap();
+
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        assert_eq!(bindings.get(&ctrl('r')), Some(KeyAction::CycleSearchMode));
+        assert_eq!(bindings.get(&ctrl('y')), Some(KeyAction::Copy));
+        assert_eq!(bindings.get(&ctrl('u')), Some(KeyAction::ClearInput));
+        assert_eq!(
+            bindings.get(&KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT)),
+            None
+        );
+    }
+}
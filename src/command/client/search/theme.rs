@@ -0,0 +1,241 @@
+use eyre::{bail, eyre, Result, WrapErr};
+
+use crate::tui::style::{Color, Modifier, Style};
+use atuin_client::settings::{self, ThemeName, ThemeStyle};
+
+/// The styles from the `[theme]` config, ready for drawing
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub highlight: Style,
+    pub selected: Style,
+    pub success: Style,
+    pub failure: Style,
+    pub time: Style,
+    pub title: Style,
+    pub alert: Style,
+    pub label: Style,
+    pub muted: Style,
+}
+
+impl Theme {
+    pub fn new(theme: &settings::Theme) -> Result<Self> {
+        let no_color = std::env::var_os("NO_COLOR").map_or(false, |v| !v.is_empty());
+        Self::with_overrides(theme, no_color)
+    }
+
+    fn with_overrides(theme: &settings::Theme, no_color: bool) -> Result<Self> {
+        let mut built = Self::builtin(if no_color {
+            ThemeName::Mono
+        } else {
+            theme.name
+        });
+
+        let overrides = [
+            ("highlight", &theme.highlight, &mut built.highlight),
+            ("selected", &theme.selected, &mut built.selected),
+            ("success", &theme.success, &mut built.success),
+            ("failure", &theme.failure, &mut built.failure),
+            ("time", &theme.time, &mut built.time),
+            ("title", &theme.title, &mut built.title),
+            ("alert", &theme.alert, &mut built.alert),
+            ("label", &theme.label, &mut built.label),
+            ("muted", &theme.muted, &mut built.muted),
+        ];
+        for (name, configured, target) in overrides {
+            if let Some(configured) = configured {
+                *target = style(configured).wrap_err_with(|| format!("invalid theme.{name}"))?;
+            }
+
+            // NO_COLOR still allows bold, underline and so on
+            if no_color {
+                target.fg = None;
+                target.bg = None;
+            }
+        }
+
+        Ok(built)
+    }
+
+    fn builtin(name: ThemeName) -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let highlight = bold.add_modifier(Modifier::UNDERLINED);
+
+        match name {
+            ThemeName::Default => Self {
+                highlight,
+                selected: bold.fg(Color::Red),
+                success: Style::default().fg(Color::Green),
+                failure: Style::default().fg(Color::Red),
+                time: Style::default().fg(Color::Blue),
+                title: bold,
+                alert: bold.fg(Color::Red),
+                label: bold,
+                muted: Style::default().fg(Color::DarkGray),
+            },
+            // light backgrounds wash out the bright colors, and dark gray is
+            // hardly dimmer than the text
+            ThemeName::Light => Self {
+                highlight,
+                selected: bold.fg(Color::Blue),
+                success: Style::default().fg(Color::Green),
+                failure: Style::default().fg(Color::Red),
+                time: Style::default().fg(Color::Magenta),
+                title: bold,
+                alert: bold.fg(Color::Red),
+                label: bold,
+                muted: Style::default().fg(Color::Gray),
+            },
+            ThemeName::Mono => Self {
+                highlight,
+                selected: bold.add_modifier(Modifier::REVERSED),
+                success: Style::default(),
+                failure: bold,
+                time: Style::default(),
+                title: bold,
+                alert: bold,
+                label: bold,
+                muted: Style::default().add_modifier(Modifier::DIM),
+            },
+        }
+    }
+}
+
+fn style(s: &ThemeStyle) -> Result<Style> {
+    let mut style = Style::default();
+
+    if let Some(fg) = &s.fg {
+        style = style.fg(parse_color(fg)?);
+    }
+    if let Some(bg) = &s.bg {
+        style = style.bg(parse_color(bg)?);
+    }
+
+    for (enabled, modifier) in [
+        (s.bold, Modifier::BOLD),
+        (s.italic, Modifier::ITALIC),
+        (s.underline, Modifier::UNDERLINED),
+        (s.reversed, Modifier::REVERSED),
+    ] {
+        if enabled {
+            style = style.add_modifier(modifier);
+        }
+    }
+
+    Ok(style)
+}
+
+fn parse_color(color: &str) -> Result<Color> {
+    let c = match color.to_lowercase().replace(['-', '_'], "").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        other => {
+            if let Some(hex) = other.strip_prefix('#') {
+                let rgb = u32::from_str_radix(hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 6)
+                    .ok_or_else(|| eyre!("invalid hex color {color:?}"))?;
+                let [_, r, g, b] = rgb.to_be_bytes();
+                Color::Rgb(r, g, b)
+            } else if let Ok(index) = other.parse() {
+                Color::Indexed(index)
+            } else {
+                bail!("unknown color {color:?}");
+            }
+        }
+    };
+
+    Ok(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tui::style::{Color, Modifier, Style};
+    use atuin_client::settings::{self, ThemeName, ThemeStyle};
+
+    use super::{parse_color, style, Theme};
+
+    #[test]
+    fn parse_colors() {
+        assert_eq!(parse_color("Dark-Gray").unwrap(), Color::DarkGray);
+        assert_eq!(parse_color("#ff8000").unwrap(), Color::Rgb(255, 128, 0));
+        assert_eq!(parse_color("208").unwrap(), Color::Indexed(208));
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("256").is_err());
+        assert!(parse_color("mauve").is_err());
+    }
+
+    #[test]
+    fn build_style() {
+        let s = ThemeStyle {
+            fg: Some("yellow".to_string()),
+            bold: true,
+            ..ThemeStyle::default()
+        };
+        assert_eq!(
+            style(&s).unwrap(),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        );
+    }
+
+    #[test]
+    fn overrides() {
+        let config = settings::Theme {
+            name: ThemeName::Light,
+            time: Some(ThemeStyle {
+                fg: Some("cyan".to_string()),
+                ..ThemeStyle::default()
+            }),
+            ..settings::Theme::default()
+        };
+
+        let theme = Theme::with_overrides(&config, false).unwrap();
+        assert_eq!(theme.time, Style::default().fg(Color::Cyan));
+        assert_eq!(theme.selected, Theme::builtin(ThemeName::Light).selected);
+
+        let invalid = settings::Theme {
+            label: Some(ThemeStyle {
+                bg: Some("mauve".to_string()),
+                ..ThemeStyle::default()
+            }),
+            ..settings::Theme::default()
+        };
+        let err = Theme::with_overrides(&invalid, false).unwrap_err();
+        assert_eq!(err.to_string(), "invalid theme.label");
+    }
+
+    #[test]
+    fn no_color() {
+        let config = settings::Theme {
+            time: Some(ThemeStyle {
+                fg: Some("cyan".to_string()),
+                underline: true,
+                ..ThemeStyle::default()
+            }),
+            ..settings::Theme::default()
+        };
+
+        let theme = Theme::with_overrides(&config, true).unwrap();
+        assert_eq!(
+            theme.time,
+            Style::default().add_modifier(Modifier::UNDERLINED)
+        );
+        assert_eq!(theme.failure, Theme::builtin(ThemeName::Mono).failure);
+    }
+}
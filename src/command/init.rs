@@ -47,9 +47,12 @@ bindkey '^[OA' _atuin_up_search_widget";
         println!("{base}");
 
         if std::env::var("ATUIN_NOBIND").is_err() {
-            const BIND_CTRL_R: &str = r#"bind -x '"\C-r": __atuin_history'"#;
-            const BIND_UP_ARROW: &str = r#"bind -x '"\e[A": __atuin_history --shell-up-key-binding'
-bind -x '"\eOA": __atuin_history --shell-up-key-binding'"#;
+            // see __atuin_history for why these are macros
+            const BIND_CTRL_R: &str = r#"bind -x '"\e[9000~": __atuin_history'
+bind '"\C-r": "\e[9000~\e[9001~"'"#;
+            const BIND_UP_ARROW: &str = r#"bind -x '"\e[9002~": __atuin_history --shell-up-key-binding'
+bind '"\e[A": "\e[9002~\e[9001~"'
+bind '"\eOA": "\e[9002~\e[9001~"'"#;
             if !self.disable_ctrl_r {
                 println!("{BIND_CTRL_R}");
             }
@@ -21,8 +21,19 @@ __atuin_history() {
     HISTORY="$(RUST_LOG=error atuin search $* -i -- "${READLINE_LINE}" 3>&1 1>&2 2>&3)"
     tput smkx
 
-    READLINE_LINE=${HISTORY}
-    READLINE_POINT=${#READLINE_LINE}
+    # a bind -x function can't accept the line, so the key bindings are macros
+    # that run this, and then whatever \e[9001~ is bound to
+    if [[ ${HISTORY} == __atuin_accept__:* ]]; then
+        HISTORY=${HISTORY#__atuin_accept__:}
+        bind '"\e[9001~": accept-line'
+    else
+        bind '"\e[9001~": ""'
+    fi
+
+    if [[ -n ${HISTORY} ]]; then
+        READLINE_LINE=${HISTORY}
+        READLINE_POINT=${#READLINE_LINE}
+    fi
 }
 
 if [[ -n "${BLE_VERSION-}" ]]; then
@@ -18,7 +18,18 @@ function _atuin_search
     set h (RUST_LOG=error atuin search $argv -i -- (commandline -b) 3>&1 1>&2 2>&3)
     commandline -f repaint
     if test -n "$h"
-        commandline -r $h
+        # the command should be ran straight away, rather than edited
+        set -l run false
+        if string match -q -- '__atuin_accept__:*' $h[1]
+            set h[1] (string replace -- __atuin_accept__: '' $h[1])
+            set run true
+        end
+
+        # command substitution splits on newlines, so join several marked commands back up
+        commandline -r (string join \n -- $h)
+        if $run
+            commandline -f execute
+        end
     end
 end
 